@@ -0,0 +1,85 @@
+//! # Backend
+//! `game_loop` only talks to a `Backend`, which shows frames and reports events.
+//! This keeps the loop independent of Piston/OpenGL, see `GameEngineData` for the
+//! Piston backend and `HeadlessBackend` for one that renders in memory.
+
+use crate::game_engine::background::Background;
+use crate::game_engine::game_board::PixelStore;
+use crate::game_engine::game_data_model::GameDataModel;
+use crate::game_engine::grid::GridOptions;
+use crate::game_engine::layout::BoardLayout;
+use crate::game_engine::input::InputEvent;
+use crate::game_engine::timestep::LoopSettings;
+
+/// Arguments for rendering a frame
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RenderArgs {
+    /// extrapolated time in seconds since the last update
+    pub ext_dt: f64,
+    /// how far between the last update and the next one, 0.0 to 1.0, set by `game_loop`,
+    /// use it to interpolate positions between updates
+    pub alpha: f64,
+    /// size of the window in points
+    pub window_size: [f64; 2],
+    /// size of the window in pixels
+    pub draw_size: [u32; 2],
+}
+
+/// Arguments for updating the game
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct UpdateArgs {
+    /// time since the last update in seconds
+    pub dt: f64,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum BackendEvent {
+    Render(RenderArgs),
+    Update(UpdateArgs),
+    Input(InputEvent),
+    Resize { window_size: [f64; 2], draw_size: [u32; 2] },
+}
+
+/// Everything a backend needs to show the board
+pub struct Frame<'a> {
+    pub pixels: &'a dyn PixelStore,
+    /// top left corner of the board in the window
+    pub offset: [f32; 2],
+    pub block_width: f32,
+    pub block_height: f32,
+    pub board_width: f32,
+    pub board_height: f32,
+    pub grid: GridOptions,
+    pub background: Background,
+}
+
+impl<'a> Frame<'a> {
+    pub fn from_model<T: GameDataModel>(pixels: &'a dyn PixelStore, model: &T) -> Self {
+        Frame::with_layout(pixels, model, &BoardLayout::from_model(model))
+    }
+
+    /// frame for a resized window, see `BoardLayout::fit`
+    pub fn with_layout<T: GameDataModel>(pixels: &'a dyn PixelStore, model: &T, layout: &BoardLayout) -> Self {
+        Frame {
+            pixels,
+            offset: layout.offset,
+            block_width: layout.block_width,
+            block_height: layout.block_height,
+            board_width: layout.board_width,
+            board_height: layout.board_height,
+            grid: model.get_grid_options(),
+            background: model.get_background(),
+        }
+    }
+}
+
+pub trait Backend {
+    /// waits for the next event, `None` once the backend is closed
+    fn poll_event(&mut self) -> Option<BackendEvent>;
+    /// shows the frame, called for every `BackendEvent::Render`
+    fn present(&mut self, frame: &Frame, args: &RenderArgs);
+    /// update rate `game_loop` uses with this backend
+    fn loop_settings(&self) -> LoopSettings {
+        LoopSettings::default()
+    }
+}
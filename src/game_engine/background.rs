@@ -0,0 +1,118 @@
+//! # Background
+//! What is drawn behind the board before every frame
+
+use std::path::{Path, PathBuf};
+use graphics::types::Color;
+use crate::BLACK;
+use crate::game_engine::backend::Frame;
+use crate::game_engine::game_board::{PixelBuffer, PixelStore};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Background {
+    /// clears the window with the color
+    Color(Color),
+    /// image stretched over the whole window, black if it can not be loaded
+    Image(PathBuf),
+    /// keeps what was drawn before, for trail effects
+    None,
+}
+
+impl Default for Background {
+    fn default() -> Self {
+        Background::Color(BLACK)
+    }
+}
+
+/// Keeps the last loaded background image, so it is not loaded again every frame
+#[derive(Debug)]
+pub struct ImageCache<T> {
+    path: Option<PathBuf>,
+    image: Option<T>,
+}
+
+impl<T> Default for ImageCache<T> {
+    fn default() -> Self {
+        ImageCache { path: None, image: None }
+    }
+}
+
+impl<T> ImageCache<T> {
+    pub fn new() -> Self {
+        ImageCache::default()
+    }
+
+    /// the image for `path`, calls `load` when the path changed, failures are cached as well
+    pub fn get_or_load<F: FnOnce(&Path) -> Option<T>>(&mut self, path: &Path, load: F) -> Option<&T> {
+        if self.path.as_deref() != Some(path) {
+            self.image = load(path);
+            self.path = Some(path.to_path_buf());
+        }
+        self.image.as_ref()
+    }
+}
+
+/// Pixels drawn since the background was last cleared, for backends whose window
+/// does not keep its content between frames, e.g. a double buffered OpenGL window
+#[derive(Clone, Debug, Default)]
+pub struct Trail {
+    pixels: PixelBuffer,
+}
+
+impl Trail {
+    pub fn new() -> Self {
+        Trail::default()
+    }
+
+    /// pixels to draw for the frame, with `Background::None` the frame is added to the trail,
+    /// any other background clears it
+    pub fn pixels_for<'a>(&'a mut self, frame: &Frame<'a>) -> &'a dyn PixelStore {
+        if frame.background != Background::None {
+            self.pixels.clear();
+            return frame.pixels;
+        }
+        frame.pixels.pixels().for_each(|p| self.pixels.set_pixel(p.point.x, p.point.y, p.color));
+        &self.pixels
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use crate::{GREEN, RED};
+    use crate::game_engine::backend::Frame;
+    use crate::game_engine::background::{Background, ImageCache, Trail};
+    use crate::game_engine::game_board::PixelBuffer;
+    use crate::game_engine::game_config::GameConfig;
+    use crate::game_engine::test_support::TestGame;
+
+    #[test]
+    fn test_image_cache() {
+        let mut cache = ImageCache::new();
+        let mut loads = 0;
+        for _ in 0..3 {
+            cache.get_or_load(Path::new("a.png"), |_| { loads += 1; Some(1) });
+        }
+        assert_eq!(cache.get_or_load(Path::new("b.png"), |_| { loads += 1; None }), None);
+        assert_eq!(loads, 2);
+    }
+
+    #[test]
+    fn test_trail() {
+        let mut game = TestGame::new(GameConfig::default());
+        game.background = Background::None;
+        let mut trail = Trail::new();
+        let mut first = PixelBuffer::new();
+        first.set(0, 0, RED);
+        let mut second = PixelBuffer::new();
+        second.set(1, 0, GREEN);
+        trail.pixels_for(&Frame::from_model(&first, &game));
+        let pixels = trail.pixels_for(&Frame::from_model(&second, &game));
+        assert_eq!((pixels.get_color(0.0, 0.0), pixels.get_color(1.0, 0.0)), (Some(RED), Some(GREEN)));
+
+        // clearing the background ends the trail
+        game.background = Background::default();
+        assert_eq!(trail.pixels_for(&Frame::from_model(&second, &game)).get_color(0.0, 0.0), None);
+        game.background = Background::None;
+        assert_eq!(trail.pixels_for(&Frame::from_model(&second, &game)).get_color(0.0, 0.0), None);
+    }
+}
@@ -0,0 +1,155 @@
+//! # Bindings
+//! Maps named actions to keys, mouse buttons and gamepad buttons, see `bindings.config`
+//!
+//! ```text
+//! move_left=Left, A
+//! fire=Space, Mouse:Left, Gamepad:0:0
+//! ```
+//!
+//! Every line binds an action to a comma separated list of buttons, see `Button::from_name`.
+//! Binding the same action on several lines adds the buttons, an empty list leaves it unbound.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use crate::game_engine::game_config::{parse_key_values, ConfigError};
+use crate::game_engine::input::{Button, InputState};
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ActionBindings {
+    actions: BTreeMap<String, Vec<Button>>,
+}
+
+impl ActionBindings {
+    pub fn new() -> Self {
+        ActionBindings::default()
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<ActionBindings, ConfigError> {
+        ActionBindings::parse(&fs::read_to_string(path)?)
+    }
+
+    pub fn parse(source: &str) -> Result<ActionBindings, ConfigError> {
+        let mut bindings = ActionBindings::new();
+        for (line, action, value) in parse_key_values(source)? {
+            bindings.actions.entry(action.to_string()).or_default();
+            for name in value.split(',').map(str::trim).filter(|n| !n.is_empty()) {
+                let button = Button::from_name(name).ok_or_else(|| ConfigError::InvalidValue {
+                    line,
+                    key: action.to_string(),
+                    value: name.to_string(),
+                })?;
+                bindings.bind(action, button);
+            }
+        }
+        Ok(bindings)
+    }
+
+    /// adds `button` to the buttons triggering `action`
+    pub fn bind(&mut self, action: &str, button: Button) {
+        let buttons = self.actions.entry(action.to_string()).or_default();
+        if !buttons.contains(&button) {
+            buttons.push(button);
+        }
+    }
+
+    pub fn unbind(&mut self, action: &str, button: Button) {
+        if let Some(buttons) = self.actions.get_mut(action) {
+            buttons.retain(|b| *b != button);
+        }
+    }
+
+    /// replaces all buttons of `action`
+    pub fn rebind(&mut self, action: &str, buttons: &[Button]) {
+        self.actions.insert(action.to_string(), Vec::new());
+        for &button in buttons {
+            self.bind(action, button);
+        }
+    }
+
+    pub fn remove_action(&mut self, action: &str) {
+        self.actions.remove(action);
+    }
+
+    /// buttons bound to `action`, empty for unknown actions
+    pub fn buttons(&self, action: &str) -> &[Button] {
+        self.actions.get(action).map(|b| b.as_slice()).unwrap_or(&[])
+    }
+
+    /// all actions in alphabetical order
+    pub fn actions(&self) -> impl Iterator<Item = &str> {
+        self.actions.keys().map(|a| a.as_str())
+    }
+
+    /// actions `button` is bound to, for use in `handle_press_events` and `handle_release_events`
+    pub fn actions_for(&self, button: &Button) -> impl Iterator<Item = &str> + '_ {
+        let button = *button;
+        self.actions.iter()
+            .filter(move |(_, buttons)| buttons.contains(&button))
+            .map(|(action, _)| action.as_str())
+    }
+
+    pub fn triggers(&self, action: &str, button: &Button) -> bool {
+        self.buttons(action).contains(button)
+    }
+
+    /// any of the buttons of `action` is held
+    pub fn is_active(&self, action: &str, input: &InputState) -> bool {
+        self.buttons(action).iter().any(|b| input.is_held(*b))
+    }
+
+    /// any of the buttons of `action` was pressed since the last update
+    pub fn just_activated(&self, action: &str, input: &InputState) -> bool {
+        self.buttons(action).iter().any(|b| input.just_pressed(*b))
+    }
+
+    /// any of the buttons of `action` was released since the last update
+    pub fn just_deactivated(&self, action: &str, input: &InputState) -> bool {
+        self.buttons(action).iter().any(|b| input.just_released(*b))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game_engine::bindings::ActionBindings;
+    use crate::game_engine::game_config::ConfigError;
+    use crate::game_engine::input::{Button, GamepadButton, GamepadHat, HatDirection, InputEvent, InputState, Key, MouseButton};
+
+    #[test]
+    fn test_parse_bindings_config() {
+        let bindings = ActionBindings::parse(include_str!("../../bindings.config")).unwrap();
+        assert_eq!(bindings.buttons("move_left"), &[Button::Keyboard(Key::Left), Button::Keyboard(Key::A)]);
+        assert_eq!(bindings.buttons("fire"), &[
+            Button::Keyboard(Key::Space),
+            Button::Mouse(MouseButton::Left),
+            Button::Gamepad(GamepadButton { gamepad: 0, button: 0 }),
+        ]);
+        assert_eq!(bindings.buttons("move_up")[1],
+            Button::Hat(GamepadHat { gamepad: 0, hat: 0, direction: HatDirection::Up }));
+        assert!(bindings.buttons("jump").is_empty());
+        assert!(matches!(ActionBindings::parse("fire=Space\njump=Spacebar"),
+            Err(ConfigError::InvalidValue { line: 2, .. })));
+        assert!(matches!(ActionBindings::parse("fire"), Err(ConfigError::Syntax { line: 1, .. })));
+    }
+
+    #[test]
+    fn test_rebind_and_poll() {
+        let mut bindings = ActionBindings::parse("fire=Space\nfire=Key:300\npause=Escape, Space").unwrap();
+        let space = Button::Keyboard(Key::Space);
+        assert_eq!(bindings.actions_for(&space).collect::<Vec<_>>(), vec!["fire", "pause"]);
+        assert!(bindings.triggers("fire", &Button::Keyboard(Key::Other(300))));
+        bindings.rebind("fire", &[Button::Keyboard(Key::F), Button::Mouse(MouseButton::Left)]);
+        bindings.unbind("pause", space);
+        assert_eq!(bindings.actions_for(&space).count(), 0);
+
+        let mut input = InputState::new();
+        input.handle(&InputEvent::Press(Button::Mouse(MouseButton::Left)));
+        assert!(bindings.is_active("fire", &input));
+        assert!(bindings.just_activated("fire", &input));
+        assert!(!bindings.is_active("pause", &input));
+        assert!(!bindings.is_active("unknown", &input));
+        input.end_frame();
+        assert!(bindings.is_active("fire", &input));
+        assert!(!bindings.just_activated("fire", &input));
+    }
+}
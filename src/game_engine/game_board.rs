@@ -1,52 +1,318 @@
-/// # Game Board
-/// game board handles the game without depending on any game library.
-/// This allows to implement our game using any game engine/backend we want.
-/// We are only modifying the game array here.
-
+//! # Game Board
+//! game board handles the game without depending on any game library.
+//! This allows to implement our game using any game engine/backend we want.
+//! We are only modifying the game array here.
 
 use std::collections::HashMap;
-use crate::game_engine::shapes::Block;
-use std::mem::swap;
+use crate::game_engine::shapes::{Block, BlendMode, MAX_COORDINATE};
+use crate::game_engine::game_data_model::GameDataModel;
 use mint::Point2;
 use graphics::types::Color;
 
+/// String keyed pixels, kept for code written against it, the engine draws into a `PixelBuffer`
 pub type PixelMap = HashMap<String, Pixel>;
 
 fn make_key(x: f32, y: f32) -> String {
-    format!("{},{}", x, y)
+    // adding 0.0 turns -0.0 into 0.0, both would print differently
+    format!("{},{}", x + 0.0, y + 0.0)
 }
 
-#[derive(Debug)]
+#[derive(Copy, Clone, Debug)]
 pub struct Pixel {
     pub point: Point2<f32>,
     pub color: Color,
 }
 
 pub struct GameBoard {
-    pub pixels: PixelMap
+    pub pixels: PixelBuffer
+}
+
+impl Default for GameBoard {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl GameBoard {
     pub fn new() -> Self {
         GameBoard {
-            pixels: HashMap::new()
+            pixels: PixelBuffer::new()
         }
     }
 }
 
-pub fn set_pixel(pixels: &mut PixelMap, x: f32, y: f32, color: Color) {
-    pixels.insert(make_key(x, y), Pixel {
-        point: Point2 { x, y },
-        color,
-    });
+/// Storage the rasterizers draw into
+pub trait PixelStore {
+    fn set_pixel(&mut self, x: f32, y: f32, color: Color);
+    fn get_color(&self, x: f32, y: f32) -> Option<Color>;
+    /// all pixels that have been set
+    fn pixels(&self) -> Box<dyn Iterator<Item = Pixel> + '_>;
+    /// smallest and largest x and y the store keeps, `[min_x, min_y, max_x, max_y]`,
+    /// lines are clipped to them, `None` if any pixel is kept
+    fn bounds(&self) -> Option<[f32; 4]> {
+        None
+    }
+}
+
+/// lines in stores without bounds are clipped to this far from the origin,
+/// f32 can not step through pixels much further out
+const UNBOUNDED: [f32; 4] = [-MAX_COORDINATE, -MAX_COORDINATE, MAX_COORDINATE, MAX_COORDINATE];
+
+impl PixelStore for PixelMap {
+    fn set_pixel(&mut self, x: f32, y: f32, color: Color) {
+        self.insert(make_key(x, y), Pixel {
+            point: Point2 { x, y },
+            color,
+        });
+    }
+
+    fn get_color(&self, x: f32, y: f32) -> Option<Color> {
+        get_pixel(self, x, y).map(|p| p.color)
+    }
+
+    fn pixels(&self) -> Box<dyn Iterator<Item = Pixel> + '_> {
+        Box::new(self.values().copied())
+    }
+}
+
+/// Sparse pixel storage with integer coordinates, what `draw_shapes` returns.
+/// Coordinates are rounded to whole pixels, the board can have any size.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PixelBuffer {
+    pixels: HashMap<(i32, i32), Color>,
+}
+
+impl PixelBuffer {
+    pub fn new() -> Self {
+        PixelBuffer::default()
+    }
+
+    pub fn set(&mut self, x: i32, y: i32, color: Color) {
+        self.pixels.insert((x, y), color);
+    }
+
+    pub fn get(&self, x: i32, y: i32) -> Option<Color> {
+        self.pixels.get(&(x, y)).copied()
+    }
+
+    pub fn unset(&mut self, x: i32, y: i32) {
+        self.pixels.remove(&(x, y));
+    }
+
+    pub fn clear(&mut self) {
+        self.pixels.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.pixels.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pixels.is_empty()
+    }
+
+    /// set pixels, in no particular order
+    pub fn iter(&self) -> impl Iterator<Item = Pixel> + '_ {
+        self.pixels.iter().map(|(&(x, y), &color)| Pixel {
+            point: Point2 { x: x as f32, y: y as f32 },
+            color,
+        })
+    }
+
+    /// converts to a `PixelMap`, for code that still consumes it
+    pub fn to_pixel_map(&self) -> PixelMap {
+        let mut pixels = PixelMap::new();
+        self.iter().for_each(|p| set_pixel(&mut pixels, p.point.x, p.point.y, p.color));
+        pixels
+    }
+}
+
+impl From<&PixelMap> for PixelBuffer {
+    fn from(pixels: &PixelMap) -> Self {
+        let mut buffer = PixelBuffer::new();
+        pixels.values().for_each(|p| buffer.set_pixel(p.point.x, p.point.y, p.color));
+        buffer
+    }
+}
+
+impl PixelStore for PixelBuffer {
+    fn set_pixel(&mut self, x: f32, y: f32, color: Color) {
+        self.set(x.round() as i32, y.round() as i32, color);
+    }
+
+    fn get_color(&self, x: f32, y: f32) -> Option<Color> {
+        self.get(x.round() as i32, y.round() as i32)
+    }
+
+    fn pixels(&self) -> Box<dyn Iterator<Item = Pixel> + '_> {
+        Box::new(self.iter())
+    }
+}
+
+/// cells of the board, see `PixelGrid::from_model`
+fn model_cells<T: GameDataModel>(model: &T) -> (usize, usize) {
+    let cells = |board: f32, block: f32| if block > 0.0 {
+        (board / block).ceil().max(0.0) as usize
+    } else {
+        model.get_map_size().max(0) as usize
+    };
+    (
+        cells(model.get_board_width(), model.get_block_width()),
+        cells(model.get_board_height(), model.get_block_height()),
+    )
+}
+
+/// Dense pixel storage with integer coordinates.
+/// Setting or reading a pixel does not allocate, pixels outside the grid are ignored.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PixelGrid {
+    width: usize,
+    height: usize,
+    cells: Vec<Option<Color>>,
+}
+
+impl PixelGrid {
+    pub fn new(width: usize, height: usize) -> Self {
+        PixelGrid {
+            width,
+            height,
+            cells: vec![None; width * height],
+        }
+    }
+
+    /// grid with one cell per block on the board, falls back to `map_size`
+    /// when the block size is not set
+    pub fn from_model<T: GameDataModel>(model: &T) -> Self {
+        let (width, height) = model_cells(model);
+        PixelGrid::new(width, height)
+    }
+
+    /// clears the grid, resizing it first when the board of the model changed size
+    pub fn reset_to_model<T: GameDataModel>(&mut self, model: &T) {
+        if model_cells(model) == (self.width, self.height) {
+            self.clear();
+        } else {
+            *self = PixelGrid::from_model(model);
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    fn offset(&self, x: i32, y: i32) -> Option<usize> {
+        if x < 0 || y < 0 || x as usize >= self.width || y as usize >= self.height {
+            return None;
+        }
+        Some(y as usize * self.width + x as usize)
+    }
+
+    /// returns false if the pixel is outside of the grid
+    pub fn set(&mut self, x: i32, y: i32, color: Color) -> bool {
+        match self.offset(x, y) {
+            Some(i) => {
+                self.cells[i] = Some(color);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn get(&self, x: i32, y: i32) -> Option<Color> {
+        self.offset(x, y).and_then(|i| self.cells[i])
+    }
+
+    pub fn unset(&mut self, x: i32, y: i32) {
+        if let Some(i) = self.offset(x, y) {
+            self.cells[i] = None;
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.cells.iter_mut().for_each(|c| *c = None);
+    }
+
+    /// set pixels, row by row
+    pub fn iter(&self) -> impl Iterator<Item = Pixel> + '_ {
+        let width = self.width;
+        self.cells.iter().enumerate().filter_map(move |(i, cell)| cell.map(|color| Pixel {
+            point: Point2 { x: (i % width) as f32, y: (i / width) as f32 },
+            color,
+        }))
+    }
+
+    /// converts to a `PixelMap`, for code that still consumes it
+    pub fn to_pixel_map(&self) -> PixelMap {
+        let mut pixels = PixelMap::new();
+        self.iter().for_each(|p| set_pixel(&mut pixels, p.point.x, p.point.y, p.color));
+        pixels
+    }
+}
+
+impl PixelStore for PixelGrid {
+    fn set_pixel(&mut self, x: f32, y: f32, color: Color) {
+        self.set(x.round() as i32, y.round() as i32, color);
+    }
+
+    fn get_color(&self, x: f32, y: f32) -> Option<Color> {
+        self.get(x.round() as i32, y.round() as i32)
+    }
+
+    fn pixels(&self) -> Box<dyn Iterator<Item = Pixel> + '_> {
+        Box::new(self.iter())
+    }
+
+    fn bounds(&self) -> Option<[f32; 4]> {
+        Some([0.0, 0.0, self.width as f32 - 1.0, self.height as f32 - 1.0])
+    }
+}
+
+pub fn set_pixel<P: PixelStore>(pixels: &mut P, x: f32, y: f32, color: Color) {
+    pixels.set_pixel(x, y, color);
 }
 
 pub fn get_pixel(pixels: &PixelMap, x: f32, y: f32) -> Option<&Pixel> {
     pixels.get(&make_key(x, y))
 }
 
+/// sets the pixel, combining the color with the one already there
+pub fn blend_pixel<P: PixelStore>(pixels: &mut P, x: f32, y: f32, color: Color, mode: BlendMode) {
+    let blended = blend_colors(pixels.get_color(x, y), color, mode);
+    pixels.set_pixel(x, y, blended);
+}
+
+/// combines `src` with `dst`, an empty `dst` always gives `src`
+pub fn blend_colors(dst: Option<Color>, src: Color, mode: BlendMode) -> Color {
+    let dst = match dst {
+        Some(dst) if mode != BlendMode::Replace => dst,
+        _ => return src,
+    };
+    let sa = src[3].clamp(0.0, 1.0);
+    let da = dst[3].clamp(0.0, 1.0);
+    let channel = |f: &dyn Fn(f32, f32) -> f32| [
+        f(src[0], dst[0]), f(src[1], dst[1]), f(src[2], dst[2]),
+    ];
+    let (rgb, a) = match mode {
+        BlendMode::Replace => unreachable!(),
+        BlendMode::AlphaOver => {
+            let a = sa + da * (1.0 - sa);
+            if a <= 0.0 {
+                return [0.0, 0.0, 0.0, 0.0];
+            }
+            (channel(&|s, d| (s * sa + d * da * (1.0 - sa)) / a), a)
+        }
+        BlendMode::Additive => (channel(&|s, d| (d + s * sa).min(1.0)), (da + sa).min(1.0)),
+        BlendMode::Multiply => (channel(&|s, d| d * (s * sa + 1.0 - sa)), da),
+    };
+    [rgb[0], rgb[1], rgb[2], a]
+}
+
 /// draw rectangle
-pub fn draw_rectangle(x: f32, y: f32, w: f32, h: f32, pixels: &mut PixelMap, color: Color) {
+pub fn draw_rectangle<P: PixelStore>(x: f32, y: f32, w: f32, h: f32, pixels: &mut P, color: Color) {
     let mut i = x;
     loop {
         let mut j = y;
@@ -54,73 +320,107 @@ pub fn draw_rectangle(x: f32, y: f32, w: f32, h: f32, pixels: &mut PixelMap, col
         loop {
             if j >= y + h { break; }
             set_pixel(pixels, i, j, color);
-            j = j + 1.0;
+            j += 1.0;
         }
-        i = i + 1.0;
+        i += 1.0;
     }
 }
 
+/// hollow rectangle, the border grows inwards from the edges
+pub fn draw_rectangle_outline<P: PixelStore>(x: f32, y: f32, w: f32, h: f32, stroke: f32, pixels: &mut P, color: Color) {
+    let sx = stroke.max(1.0).min(w);
+    let sy = stroke.max(1.0).min(h);
+    draw_rectangle(x, y, w, sy, pixels, color);
+    draw_rectangle(x, y + h - sy, w, sy, pixels, color);
+    draw_rectangle(x, y + sy, sx, h - 2.0 * sy, pixels, color);
+    draw_rectangle(x + w - sx, y + sy, sx, h - 2.0 * sy, pixels, color);
+}
+
 /// Drawing ellipse with pixels
-pub fn draw_ellipse(cx: f32, cy: f32, a: f32, b: f32, points: &mut PixelMap, color: Color) {
-    let mut plot_ellipse = |x: f32, y: f32| {
+pub fn draw_ellipse<P: PixelStore>(cx: f32, cy: f32, a: f32, b: f32, points: &mut P, color: Color) {
+    ellipse_quadrant(a, b, |x, y| {
         set_pixel(points, cx + x, cy + y, color);
         set_pixel(points, cx + x, cy - y, color);
         set_pixel(points, cx - x, cy + y, color);
         set_pixel(points, cx - x, cy - y, color);
-    };
+    });
+}
+
+/// Filled ellipse, spans between the outline points so it covers the outline exactly
+pub fn fill_ellipse<P: PixelStore>(cx: f32, cy: f32, a: f32, b: f32, points: &mut P, color: Color) {
+    ellipse_quadrant(a, b, |x, y| {
+        draw_straight_line(cy + y, cx - x, cx + x, points, color, false);
+        draw_straight_line(cy - y, cx - x, cx + x, points, color, false);
+    });
+}
+
+/// ellipse outline `stroke` pixels wide, growing inwards
+pub fn draw_thick_ellipse<P: PixelStore>(cx: f32, cy: f32, a: f32, b: f32, stroke: f32, points: &mut P, color: Color) {
+    if stroke <= 1.0 {
+        return draw_ellipse(cx, cy, a, b, points, color);
+    }
+    let mut inner = PixelBuffer::new();
+    if a - stroke >= 0.0 && b - stroke >= 0.0 {
+        fill_ellipse(cx, cy, a - stroke, b - stroke, &mut inner, color);
+    }
+    draw_ring(&inner, points, color, |ring| fill_ellipse(cx, cy, a, b, ring, color));
+}
+
+/// walks one quadrant of the ellipse outline, other quadrants are mirrored by the caller
+fn ellipse_quadrant<F: FnMut(f32, f32)>(a: f32, b: f32, mut plot: F) {
     let a2 = a * a;
     let b2 = b * b;
     let mut x = 0.0;
     let mut y = b;
     let mut p = b2 + (a2 * (1.0 - 4.0 * b) - 2.0) / 4.0;
     let mut d_pe = 3.0 * b2;
-    let mut d2_pe = 2.0 * b2;
+    let d2_pe = 2.0 * b2;
     let mut d_pse = d_pe - 2.0 * a2 * (b - 1.0);
-    let mut d2_pse = d2_pe + 2.0 * a2;
-    plot_ellipse(x, y);
+    let d2_pse = d2_pe + 2.0 * a2;
+    plot(x, y);
     while d_pse < 2.0 * a2 + 3.0 * b2 {
         if p < 0.0 {
-            p = p + d_pe;
-            d_pe = d_pe + d2_pe;
-            d_pse = d_pse + d2_pe;
+            p += d_pe;
+            d_pe += d2_pe;
+            d_pse += d2_pe;
         } else {
-            p = p + d_pse;
-            d_pe = d_pe + d2_pe;
-            d_pse = d_pse + d2_pse;
+            p += d_pse;
+            d_pe += d2_pe;
+            d_pse += d2_pse;
             y -= 1.0;
         }
         x += 1.0;
-        plot_ellipse(x, y);
+        plot(x, y);
     }
     // let mut d_pse = d_pe - 2.0 * a2 * (b - 1.0);
     // let mut d2_pse = d2_pe + 2.0 * a2;
-    p = p - (a2 * (4.0 * y - 3.0) + b2 * (4.0 * x + 3.0) + 2.0) / 4.0;
-    let mut d2_ps = 2.0 * a2;
+    p -= (a2 * (4.0 * y - 3.0) + b2 * (4.0 * x + 3.0) + 2.0) / 4.0;
+    let d2_ps = 2.0 * a2;
     d_pse = 2.0 * b2 + 3.0 * a2;
     while y > 0.0 {
         if p > 0.0 {
-            p = p + d_pe;
-            d_pe = d_pe + d2_ps;
-            d_pse = d_pse + d2_ps;
+            p += d_pe;
+            d_pe += d2_ps;
+            d_pse += d2_ps;
         } else {
-            p = p + d_pse;
-            d_pe = d_pe + d2_ps;
-            d_pse = d_pse + d2_pse;
+            p += d_pse;
+            d_pe += d2_ps;
+            d_pse += d2_pse;
             x += 1.0;
         }
         y -= 1.0;
-        plot_ellipse(x, y);
+        plot(x, y);
     }
 }
 
-pub fn draw_line(_x1: f32, _y1: f32, _x2: f32, _y2: f32, points: &mut PixelMap, color: Color) {
-    let mut x1 = _x1;
-    let mut x2 = _x2;
-    let mut y1 = _y1;
-    let mut y2 = _y2;
+pub fn draw_line<P: PixelStore>(x1: f32, y1: f32, x2: f32, y2: f32, points: &mut P, color: Color) {
+    let (x1, y1, x2, y2) = match clip_line(x1, y1, x2, y2, points.bounds().unwrap_or(UNBOUNDED)) {
+        Some(line) => line,
+        None => return,
+    };
     // difference between points
-    let dx = (x2 - x1);
-    let dy = (y2 - y1);
+    let dx = x2 - x1;
+    let dy = y2 - y1;
     // if any of dx/dy is zero ... it's a straight line along axis
     if dx == 0.0 {
         return draw_straight_line(x1, y1, y2, points, color, true);
@@ -129,14 +429,14 @@ pub fn draw_line(_x1: f32, _y1: f32, _x2: f32, _y2: f32, points: &mut PixelMap,
         return draw_straight_line(y1, x1, x2, points, color, false);
     }
     // lets start drawing
-    let mut x = x1;
-    let mut y = y1;
+    let mut x;
+    let mut y;
     let dx1 = dx.abs();
     let dy1 = dy.abs();
     let mut px = 2.0 * dy1 - dx1;
     let mut py = 2.0 * dx1 - dy1;
-    let mut xe;
-    let mut ye;
+    let xe;
+    let ye;
     //
     if dy1 <= dx1 {
         // find the starting point.
@@ -150,17 +450,17 @@ pub fn draw_line(_x1: f32, _y1: f32, _x2: f32, _y2: f32, points: &mut PixelMap,
             xe = x1;
         }
         set_pixel(points, x, y, color);
-        while x < xe {
-            x = x + 1.0;
+        for _ in 0..steps(x, xe) {
+            x += 1.0;
             if px < 0.0 {
-                px = px + 2.0 * dy1;
+                px += 2.0 * dy1;
             } else {
                 if (dx < 0.0 && dy < 0.0) || (dx > 0.0 && dy > 0.0) {
-                    y = y + 1.0
+                    y += 1.0
                 } else {
-                    y = y - 1.0
+                    y -= 1.0
                 }
-                px = px + 2.0 * (dy1 - dx1);
+                px += 2.0 * (dy1 - dx1);
             }
             set_pixel(points, x, y, color);
         }
@@ -175,58 +475,189 @@ pub fn draw_line(_x1: f32, _y1: f32, _x2: f32, _y2: f32, points: &mut PixelMap,
             ye = y1;
         }
         set_pixel(points, x, y, color);
-        while y < ye {
-            y = y + 1.0;
+        for _ in 0..steps(y, ye) {
+            y += 1.0;
             if py <= 0.0 {
-                py = py + 2.0 * dx1;
+                py += 2.0 * dx1;
             } else {
                 if (dx < 0.0 && dy < 0.0) || (dx > 0.0 && dy > 0.0) {
-                    x = x + 1.0
+                    x += 1.0
                 } else {
-                    x = x - 1.0;
+                    x -= 1.0;
                 }
-                py = py + 2.0 * (dx1 - dy1);
+                py += 2.0 * (dx1 - dy1);
             }
             set_pixel(points, x, y, color);
         }
     }
 }
 
-fn draw_straight_line(fixed_axis: f32, _p1: f32, _p2: f32, points: &mut PixelMap, color: Color, if_vertical: bool) {
-    let mut p1 = _p1;
-    let mut p2 = _p2;
-    if _p2 < _p1 {
-        p1 = _p2;
-        p2 = _p1;
+/// pixel steps from `from` to `to`, as many as `while from < to { from += 1.0 }` takes
+fn steps(from: f32, to: f32) -> u32 {
+    (to - from).ceil().max(0.0) as u32
+}
+
+/// the part of the line inside `bounds`, clipped ends are rounded to whole pixels,
+/// `None` if the line misses them
+fn clip_line(x1: f32, y1: f32, x2: f32, y2: f32, bounds: [f32; 4]) -> Option<(f32, f32, f32, f32)> {
+    if !(x1.is_finite() && y1.is_finite() && x2.is_finite() && y2.is_finite()) {
+        return None;
+    }
+    let [min_x, min_y, max_x, max_y] = bounds;
+    let inside = |x: f32, y: f32| x >= min_x && x <= max_x && y >= min_y && y <= max_y;
+    if inside(x1, y1) && inside(x2, y2) {
+        return Some((x1, y1, x2, y2));
+    }
+    // Liang-Barsky, in f64 so far away points do not overflow
+    let (dx, dy) = (x2 as f64 - x1 as f64, y2 as f64 - y1 as f64);
+    let (mut t0, mut t1) = (0.0f64, 1.0f64);
+    // edge the line enters and leaves through, its coordinate is taken as is,
+    // computing it from far away points would lose the precision
+    let (mut enter, mut leave) = (None, None);
+    let edges = [
+        (-dx, x1 as f64 - min_x as f64, min_x), (dx, max_x as f64 - x1 as f64, max_x),
+        (-dy, y1 as f64 - min_y as f64, min_y), (dy, max_y as f64 - y1 as f64, max_y),
+    ];
+    for (i, &(p, q, edge)) in edges.iter().enumerate() {
+        if p == 0.0 {
+            if q < 0.0 {
+                return None;
+            }
+        } else if p < 0.0 && q / p > t0 {
+            t0 = q / p;
+            enter = Some((i < 2, edge));
+        } else if p > 0.0 && q / p < t1 {
+            t1 = q / p;
+            leave = Some((i < 2, edge));
+        }
+    }
+    if t0 > t1 {
+        return None;
+    }
+    let at = |t: f64, edge: Option<(bool, f32)>| {
+        let x = ((x1 as f64 + t * dx).round() as f32).max(min_x).min(max_x);
+        let y = ((y1 as f64 + t * dy).round() as f32).max(min_y).min(max_y);
+        match edge {
+            Some((true, edge)) => (edge, y),
+            Some((false, edge)) => (x, edge),
+            None => (x1, y1),
+        }
+    };
+    let (a, b) = at(t0, enter);
+    let (c, d) = match leave {
+        None => (x2, y2),
+        leave => at(t1, leave),
+    };
+    Some((a, b, c, d))
+}
+
+fn draw_straight_line<P: PixelStore>(fixed_axis: f32, p1: f32, p2: f32, points: &mut P, color: Color, if_vertical: bool) {
+    let [min_x, min_y, max_x, max_y] = points.bounds().unwrap_or(UNBOUNDED);
+    let (min, max) = if if_vertical { (min_y, max_y) } else { (min_x, max_x) };
+    let (mut p1, mut p2) = if p2 < p1 { (p2, p1) } else { (p1, p2) };
+    if !(p1.is_finite() && p2.is_finite() && fixed_axis.is_finite()) {
+        return;
+    }
+    // skips whole pixels, so the pixels left keep their position
+    if p1 < min {
+        p1 += (min - p1).floor();
+    }
+    p2 = p2.min(max);
+    if p1 > p2 {
+        return;
     }
     let mut change_axis = p1;
-    if if_vertical {
-        while change_axis <= p2 {
+    // as many as `while change_axis <= p2` takes
+    for _ in 0..=(p2 - p1).floor() as u32 {
+        if if_vertical {
             set_pixel(points, fixed_axis, change_axis, color);
-            change_axis = change_axis + 1.0;
-        }
-    } else {
-        while change_axis <= p2 {
+        } else {
             set_pixel(points, change_axis, fixed_axis, color);
-            change_axis = change_axis + 1.0;
         }
+        change_axis += 1.0;
     }
-    return;
 }
 
-pub fn draw_polygon(point_list: &Vec<f32>, block: &Block, pixels: &mut PixelMap) {
+/// line `stroke` pixels wide, every pixel of the line is drawn as a square
+pub fn draw_thick_line<P: PixelStore>(x1: f32, y1: f32, x2: f32, y2: f32, stroke: f32, points: &mut P, color: Color) {
+    if stroke <= 1.0 {
+        return draw_line(x1, y1, x2, y2, points, color);
+    }
+    draw_line(x1, y1, x2, y2, &mut SquareBrush::new(points, stroke), color);
+}
+
+/// draws a square of `size` pixels centered on every pixel set through it
+struct SquareBrush<'a, P: PixelStore> {
+    pixels: &'a mut P,
+    from: f32,
+    to: f32,
+}
+
+impl<'a, P: PixelStore> SquareBrush<'a, P> {
+    fn new(pixels: &'a mut P, size: f32) -> Self {
+        let size = size.round().max(1.0);
+        let from = -((size - 1.0) / 2.0).floor();
+        SquareBrush { pixels, from, to: from + size - 1.0 }
+    }
+}
+
+impl<'a, P: PixelStore> PixelStore for SquareBrush<'a, P> {
+    fn set_pixel(&mut self, x: f32, y: f32, color: Color) {
+        let mut i = self.from;
+        while i <= self.to {
+            let mut j = self.from;
+            while j <= self.to {
+                self.pixels.set_pixel(x + i, y + j, color);
+                j += 1.0;
+            }
+            i += 1.0;
+        }
+    }
+
+    fn get_color(&self, x: f32, y: f32) -> Option<Color> {
+        self.pixels.get_color(x, y)
+    }
+
+    fn pixels(&self) -> Box<dyn Iterator<Item = Pixel> + '_> {
+        self.pixels.pixels()
+    }
+
+    /// squares centered outside of the store still reach into it
+    fn bounds(&self) -> Option<[f32; 4]> {
+        self.pixels.bounds().map(|[x1, y1, x2, y2]| [x1 - self.to, y1 - self.to, x2 - self.from, y2 - self.from])
+    }
+}
+
+/// closed polygon outline in the block color
+pub fn draw_polygon<P: PixelStore>(point_list: &[f32], block: &Block, pixels: &mut P) {
+    draw_polygon_outline(point_list, block, pixels, Color::from(block.color));
+}
+
+/// closed polygon outline in the given color, `block.stroke` pixels wide
+pub fn draw_polygon_outline<P: PixelStore>(point_list: &[f32], block: &Block, pixels: &mut P, color: Color) {
+    draw_path(point_list, block, pixels, color, true);
+}
+
+/// open polyline in the block color, the last point is not connected to the first
+pub fn draw_polyline<P: PixelStore>(point_list: &[f32], block: &Block, pixels: &mut P) {
+    draw_path(point_list, block, pixels, Color::from(block.color), false);
+}
+
+/// draws lines between consecutive points, and from the last to the first one if closed
+fn draw_path<P: PixelStore>(point_list: &[f32], block: &Block, pixels: &mut P, color: Color, closed: bool) {
     let mut p: Vec<f32> = Vec::new();
-    point_list.chunks(2)
+    let closing = if closed && point_list.len() >= 6 { &point_list[..2] } else { &[] };
+    point_list.chunks(2).chain(closing.chunks(2))
         // parse through the pairs
         .for_each(|item| {
             // add to point list
             p.extend(item);
             // we have 2 points, so draw the line
             if p.len() == 4 {
-                draw_line(
+                draw_thick_line(
                     block.x + p[0], block.y + p[1],
-                    block.x + p[2], block.y + p[3], pixels,
-                    Color::from(block.color),
+                    block.x + p[2], block.y + p[3], block.stroke,
+                    pixels, color,
                 );
                 // only keep last two points
                 p = vec![p[2], p[3]];
@@ -234,11 +665,42 @@ pub fn draw_polygon(point_list: &Vec<f32>, block: &Block, pixels: &mut PixelMap)
         });
 }
 
-pub fn draw_circle(x: f32, y: f32, r: f32, points: &mut PixelMap, color: Color) {
-    let mut x0 = 0.0;
-    let mut y0 = r;
-    let mut d = 3.0 - 2.0 * r;
-    while y0 >= x0 {
+/// Scanline fill using the even-odd rule, so concave and self intersecting polygons work.
+pub fn fill_polygon<P: PixelStore>(point_list: &[f32], block: &Block, pixels: &mut P, color: Color) {
+    let vertices: Vec<(f32, f32)> = point_list.chunks_exact(2)
+        .map(|p| (block.x + p[0], block.y + p[1]))
+        .collect();
+    if vertices.len() < 3 {
+        return;
+    }
+    let min_y = vertices.iter().map(|v| v.1).fold(f32::INFINITY, f32::min);
+    let max_y = vertices.iter().map(|v| v.1).fold(f32::NEG_INFINITY, f32::max);
+    let mut crossings = Vec::new();
+    let mut y = min_y.ceil();
+    while y <= max_y {
+        crossings.clear();
+        for (i, &(x1, y1)) in vertices.iter().enumerate() {
+            let (x2, y2) = vertices[(i + 1) % vertices.len()];
+            // half open, so a vertex shared by two edges is only counted once
+            if (y1 <= y && y < y2) || (y2 <= y && y < y1) {
+                crossings.push(x1 + (y - y1) * (x2 - x1) / (y2 - y1));
+            }
+        }
+        crossings.sort_by(|a, b| a.total_cmp(b));
+        for span in crossings.chunks_exact(2) {
+            let (from, to) = (span[0].ceil(), span[1].floor());
+            if from <= to {
+                draw_straight_line(y, from, to, pixels, color, false);
+            }
+        }
+        y += 1.0;
+    }
+    // edges are not always covered by the spans, e.g. the bottom edge
+    draw_path(point_list, &Block { stroke: 1.0, ..*block }, pixels, color, true);
+}
+
+pub fn draw_circle<P: PixelStore>(x: f32, y: f32, r: f32, points: &mut P, color: Color) {
+    circle_octant(r, |x0, y0| {
         set_pixel(points, x + x0, y - y0, color);
         set_pixel(points, x + y0, y - x0, color);
         set_pixel(points, x + y0, y + x0, color);
@@ -247,6 +709,47 @@ pub fn draw_circle(x: f32, y: f32, r: f32, points: &mut PixelMap, color: Color)
         set_pixel(points, x - y0, y + x0, color);
         set_pixel(points, x - y0, y - x0, color);
         set_pixel(points, x - x0, y - y0, color);
+    });
+}
+
+/// Filled circle, spans between the outline points so it covers the outline exactly
+pub fn fill_circle<P: PixelStore>(x: f32, y: f32, r: f32, points: &mut P, color: Color) {
+    circle_octant(r, |x0, y0| {
+        draw_straight_line(y - y0, x - x0, x + x0, points, color, false);
+        draw_straight_line(y + y0, x - x0, x + x0, points, color, false);
+        draw_straight_line(y - x0, x - y0, x + y0, points, color, false);
+        draw_straight_line(y + x0, x - y0, x + y0, points, color, false);
+    });
+}
+
+/// circle outline `stroke` pixels wide, growing inwards
+pub fn draw_thick_circle<P: PixelStore>(x: f32, y: f32, r: f32, stroke: f32, points: &mut P, color: Color) {
+    if stroke <= 1.0 {
+        return draw_circle(x, y, r, points, color);
+    }
+    let mut inner = PixelBuffer::new();
+    if r - stroke >= 0.0 {
+        fill_circle(x, y, r - stroke, &mut inner, color);
+    }
+    draw_ring(&inner, points, color, |ring| fill_circle(x, y, r, ring, color));
+}
+
+/// sets the pixels of the outer shape that are not part of the inner one
+fn draw_ring<P: PixelStore, F: FnOnce(&mut PixelBuffer)>(inner: &PixelBuffer, points: &mut P, color: Color, outer: F) {
+    let mut ring = PixelBuffer::new();
+    outer(&mut ring);
+    ring.iter()
+        .filter(|p| inner.get_color(p.point.x, p.point.y).is_none())
+        .for_each(|p| set_pixel(points, p.point.x, p.point.y, color));
+}
+
+/// walks one octant of the circle outline, other octants are mirrored by the caller
+fn circle_octant<F: FnMut(f32, f32)>(r: f32, mut plot: F) {
+    let mut x0 = 0.0;
+    let mut y0 = r;
+    let mut d = 3.0 - 2.0 * r;
+    while y0 >= x0 {
+        plot(x0, y0);
         x0 += 1.0;
         if d < 0.0 {
             d += 4.0 * x0 + 6.0;
@@ -255,4 +758,324 @@ pub fn draw_circle(x: f32, y: f32, r: f32, points: &mut PixelMap, color: Color)
             d += 4.0 * (x0 - y0) + 10.0;
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{GREEN, RED};
+    use crate::game_engine::{draw_shapes, draw_shapes_into, draw_layers};
+    use crate::game_engine::layers::Layers;
+    use crate::game_engine::game_board::{PixelBuffer, PixelGrid, PixelMap, PixelStore, blend_colors, draw_line, fill_polygon, set_pixel};
+    use crate::game_engine::game_config::GameConfig;
+    use crate::game_engine::test_support::TestGame;
+    use crate::game_engine::shape_file::parse_shapes;
+    use crate::game_engine::shapes::{Block, BlockBuilder, BlendMode, FillMode, ShapeKind};
+    use crate::game_engine::snapshot::{assert_snapshot, to_snapshot};
+
+    fn golden(name: &str) -> String {
+        format!("{}/snapshots/{}.snap", env!("CARGO_MANIFEST_DIR"), name)
+    }
+
+    #[test]
+    fn test_draw_game_cfg() {
+        let (blocks, points) = parse_shapes(include_str!("../../game_cfg")).unwrap();
+        assert_snapshot(golden("game_cfg"), &draw_shapes(&blocks, &points));
+    }
+
+    #[test]
+    fn test_draw_lines() {
+        let mut points = Vec::new();
+        let blocks = vec![
+            BlockBuilder::line(0.0, 0.0).points(vec![0.0, 0.0, 9.0, 3.0], &mut points).build(),
+            BlockBuilder::line(0.0, 0.0).points(vec![2.0, 9.0, 0.0, 4.0], &mut points).build(),
+            BlockBuilder::line(0.0, 0.0).points(vec![4.0, 5.0, 4.0, 8.0], &mut points).build(),
+            BlockBuilder::line(0.0, 0.0).points(vec![9.0, 9.0, 6.0, 9.0], &mut points).build(),
+        ];
+        assert_snapshot(golden("lines"), &draw_shapes(&blocks, &points));
+    }
+
+    #[test]
+    fn test_draw_circles() {
+        let mut points = Vec::new();
+        let blocks = vec![
+            BlockBuilder::circle(0.0, 0.0).points(vec![6.0, 6.0, 5.0], &mut points).build(),
+            BlockBuilder::circle(14.0, 0.0).points(vec![0.0, 2.0, 1.0], &mut points).build(),
+        ];
+        assert_snapshot(golden("circles"), &draw_shapes(&blocks, &points));
+    }
+
+    #[test]
+    fn test_draw_ellipses() {
+        let mut points = Vec::new();
+        let blocks = vec![
+            BlockBuilder::ellipse(0.0, 0.0).points(vec![8.0, 4.0, 7.0, 3.0], &mut points).build(),
+            BlockBuilder::ellipse(0.0, 0.0).points(vec![4.0, 14.0, 2.0, 5.0], &mut points).build(),
+        ];
+        assert_snapshot(golden("ellipses"), &draw_shapes(&blocks, &points));
+    }
+
+    #[test]
+    fn test_draw_rectangles_and_polygons() {
+        let mut points = Vec::new();
+        let blocks = vec![
+            BlockBuilder::rect(1.0, 1.0).points(vec![0.0, 0.0, 4.0, 3.0], &mut points)
+                .color(vec![0.0, 0.0, 1.0, 1.0]).build(),
+            BlockBuilder::polygon(6.0, 0.0)
+                .points(vec![0.0, 0.0, 6.0, 0.0, 6.0, 6.0, 3.0, 3.0, 0.0, 6.0, 0.0, 0.0], &mut points)
+                .build(),
+        ];
+        assert_snapshot(golden("rectangles_and_polygons"), &draw_shapes(&blocks, &points));
+    }
+
+    #[test]
+    fn test_draw_polygon_and_polyline() {
+        let (blocks, points) = parse_shapes(
+            "- Polygon\n  P, 0.0, 0.0, 8.0, 0.0, 4.0, 6.0\n#\n- Polyline\n  P, 12.0, 0.0, 20.0, 0.0, 16.0, 6.0\n#\n"
+        ).unwrap();
+        let pixels = draw_shapes(&blocks, &points);
+        // closing edge of the polygon
+        assert!(pixels.get_color(2.0, 3.0).is_some());
+        assert!(pixels.get_color(14.0, 3.0).is_none());
+        assert_snapshot(golden("polygon_and_polyline"), &pixels);
+    }
+
+    #[test]
+    fn test_draw_filled_shapes() {
+        let mut points = Vec::new();
+        let blocks = vec![
+            BlockBuilder::circle(0.0, 0.0).points(vec![6.0, 6.0, 5.0], &mut points)
+                .fill(FillMode::Filled).build(),
+            BlockBuilder::ellipse(0.0, 0.0).points(vec![22.0, 6.0, 7.0, 4.0], &mut points)
+                .fill(FillMode::Both).color(vec![0.0, 0.0, 1.0, 1.0]).build(),
+            // concave arrow head, the notch must stay empty
+            BlockBuilder::polygon(0.0, 14.0)
+                .points(vec![0.0, 0.0, 12.0, 5.0, 0.0, 10.0, 4.0, 5.0], &mut points)
+                .fill(FillMode::Filled).color(vec![0.0, 1.0, 0.0, 1.0]).build(),
+            BlockBuilder::polygon(16.0, 14.0)
+                .points(vec![0.0, 0.0, 10.0, 0.0, 10.0, 10.0, 5.0, 3.0, 0.0, 10.0, 0.0, 0.0], &mut points)
+                .fill(FillMode::Both).outline_color(vec![1.0, 0.0, 0.0, 1.0]).build(),
+        ];
+        let pixels = draw_shapes(&blocks, &points);
+        let white = [1.0, 1.0, 1.0, 1.0];
+        assert_eq!(pixels.get_color(1.0, 6.0), Some(white));
+        assert_eq!(pixels.get_color(6.0, 6.0), Some(white));
+        // notch of the arrow head
+        assert_eq!(pixels.get_color(2.0, 19.0), None);
+        assert_snapshot(golden("filled_shapes"), &pixels);
+    }
+
+    #[test]
+    fn test_fill_covers_outline() {
+        let mut points = Vec::new();
+        let outline = vec![
+            BlockBuilder::circle(0.0, 0.0).points(vec![9.0, 9.0, 7.0], &mut points).build(),
+            BlockBuilder::ellipse(0.0, 0.0).points(vec![30.0, 9.0, 9.0, 4.0], &mut points).build(),
+        ];
+        let filled: Vec<Block> = outline.iter()
+            .map(|b| Block { fill: FillMode::Filled, color: GREEN, ..*b })
+            .collect();
+        let outline_pixels = draw_shapes(&outline, &points);
+        let filled_pixels = draw_shapes(&filled, &points);
+        for p in outline_pixels.iter() {
+            assert_eq!(filled_pixels.get_color(p.point.x, p.point.y), Some(GREEN));
+        }
+    }
+
+    #[test]
+    fn test_draw_strokes() {
+        let mut points = Vec::new();
+        let blocks = vec![
+            BlockBuilder::rect(0.0, 0.0).points(vec![0.0, 0.0, 10.0, 7.0], &mut points)
+                .fill(FillMode::Outline).stroke(2.0).build(),
+            BlockBuilder::rect(12.0, 0.0).points(vec![0.0, 0.0, 6.0, 7.0], &mut points)
+                .fill(FillMode::Both).outline_color(vec![1.0, 0.0, 0.0, 1.0]).build(),
+            BlockBuilder::line(0.0, 10.0).points(vec![0.0, 0.0, 12.0, 4.0], &mut points)
+                .stroke(3.0).build(),
+            BlockBuilder::circle(0.0, 0.0).points(vec![26.0, 14.0, 6.0], &mut points)
+                .stroke(2.0).color(vec![0.0, 1.0, 0.0, 1.0]).build(),
+            BlockBuilder::ellipse(0.0, 0.0).points(vec![42.0, 14.0, 8.0, 5.0], &mut points)
+                .stroke(3.0).color(vec![0.0, 0.0, 1.0, 1.0]).build(),
+            BlockBuilder::polygon(0.0, 22.0)
+                .points(vec![2.0, 2.0, 12.0, 2.0, 7.0, 9.0, 2.0, 2.0], &mut points)
+                .stroke(2.0).build(),
+        ];
+        let pixels = draw_shapes(&blocks, &points);
+        // hollow rectangle
+        assert_eq!(pixels.get_color(1.0, 1.0), Some([1.0, 1.0, 1.0, 1.0]));
+        assert_eq!(pixels.get_color(2.0, 2.0), None);
+        assert_snapshot(golden("strokes"), &pixels);
+    }
+
+    #[test]
+    fn test_blend_colors() {
+        let half_red = [1.0, 0.0, 0.0, 0.5];
+        let blue = [0.0, 0.0, 1.0, 1.0];
+        assert_eq!(blend_colors(Some(blue), half_red, BlendMode::Replace), half_red);
+        assert_eq!(blend_colors(None, half_red, BlendMode::AlphaOver), half_red);
+        assert_eq!(blend_colors(Some(blue), half_red, BlendMode::AlphaOver), [0.5, 0.0, 0.5, 1.0]);
+        assert_eq!(blend_colors(Some(blue), half_red, BlendMode::Additive), [0.5, 0.0, 1.0, 1.0]);
+        assert_eq!(blend_colors(Some([0.8, 0.8, 0.8, 1.0]), half_red, BlendMode::Multiply), [0.8, 0.4, 0.4, 1.0]);
+    }
+
+    #[test]
+    fn test_draw_blended_blocks() {
+        let mut points = Vec::new();
+        let blocks = vec![
+            BlockBuilder::rect(0.0, 0.0).points(vec![0.0, 0.0, 4.0, 4.0], &mut points)
+                .color(vec![0.0, 0.0, 1.0, 1.0]).build(),
+            // outline and fill overlap, the block must only be blended once
+            BlockBuilder::circle(0.0, 0.0).points(vec![3.0, 3.0, 2.0], &mut points)
+                .fill(FillMode::Both).outline_color(vec![1.0, 0.0, 0.0, 0.5])
+                .color(vec![1.0, 0.0, 0.0, 0.5]).blend(BlendMode::AlphaOver).build(),
+        ];
+        let pixels = draw_shapes(&blocks, &points);
+        assert_eq!(pixels.get_color(3.0, 3.0), Some([0.5, 0.0, 0.5, 1.0]));
+        assert_eq!(pixels.get_color(3.0, 5.0), Some([1.0, 0.0, 0.0, 0.5]));
+        assert_eq!(pixels.get_color(0.0, 0.0), Some([0.0, 0.0, 1.0, 1.0]));
+    }
+
+    #[test]
+    fn test_draw_layers() {
+        let mut points = Vec::new();
+        let blocks = vec![
+            BlockBuilder::rect(0.0, 0.0).points(vec![0.0, 0.0, 2.0, 2.0], &mut points)
+                .color(vec![1.0, 0.0, 0.0, 1.0]).layer(2).build(),
+            BlockBuilder::rect(0.0, 0.0).points(vec![1.0, 1.0, 2.0, 2.0], &mut points)
+                .color(vec![0.0, 1.0, 0.0, 1.0]).layer(1).build(),
+            BlockBuilder::rect(0.0, 0.0).points(vec![1.0, 0.0, 2.0, 2.0], &mut points)
+                .color(vec![0.0, 0.0, 1.0, 1.0]).layer(1).build(),
+        ];
+        let pixels = draw_shapes(&blocks, &points);
+        // layer 2 is drawn last, even though it comes first
+        assert_eq!(pixels.get_color(1.0, 1.0), Some(RED));
+        // same layer keeps the block order
+        assert_eq!(pixels.get_color(2.0, 1.0), Some([0.0, 0.0, 1.0, 1.0]));
+        let mut layers = Layers::new();
+        layers.hide(2);
+        let pixels = draw_layers(&blocks, &points, &layers);
+        assert_eq!(pixels.get_color(0.0, 0.0), None);
+        assert_eq!(pixels.get_color(1.0, 2.0), Some(GREEN));
+    }
+
+    #[test]
+    fn test_transformed_blocks() {
+        let mut points = Vec::new();
+        let blocks = vec![
+            // rect spinning around its center
+            BlockBuilder::rect(10.0, 10.0).points(vec![-4.0, -2.0, 8.0, 4.0], &mut points)
+                .rotation_degrees(30.0).build(),
+            BlockBuilder::circle(30.0, 10.0).points(vec![0.0, 0.0, 5.0], &mut points)
+                .scale(1.5, 0.5).color(vec![0.0, 1.0, 0.0, 1.0]).build(),
+            BlockBuilder::ellipse(10.0, 26.0).points(vec![0.0, 0.0, 8.0, 3.0], &mut points)
+                .rotation_degrees(45.0).fill(FillMode::Filled).color(vec![0.0, 0.0, 1.0, 1.0]).build(),
+            BlockBuilder::polygon(24.0, 22.0).points(vec![0.0, 0.0, 6.0, 0.0, 0.0, 6.0], &mut points)
+                .pivot(2.0, 2.0).rotation_degrees(90.0).scale(2.0, 2.0).build(),
+        ];
+        let pixels = draw_shapes(&blocks, &points);
+        assert_snapshot(golden("transforms"), &pixels);
+        // the snapshot rounds positions, the pixels themselves must already be on the grid
+        let on_grid = |pixels: &PixelBuffer| pixels.iter().all(|p| p.point.x.fract() == 0.0 && p.point.y.fract() == 0.0);
+        assert!(on_grid(&pixels));
+        let line = BlockBuilder::line(0.5, 0.0).points(vec![0.0, 0.0, 6.0, 3.0], &mut points)
+            .rotation_degrees(10.0).build();
+        assert!(on_grid(&draw_shapes(&[line], &points)));
+
+        // scaling without rotation keeps rects axis aligned
+        let stretched = BlockBuilder::rect(0.0, 0.0).points(vec![1.0, 1.0, 2.0, 2.0], &mut points)
+            .scale(2.0, 1.0).build();
+        let wide = BlockBuilder::rect(0.0, 0.0).points(vec![2.0, 1.0, 4.0, 2.0], &mut points).build();
+        assert_eq!(to_snapshot(&draw_shapes(&[stretched], &points)), to_snapshot(&draw_shapes(&[wide], &points)));
+    }
+
+    #[test]
+    fn test_draw_skips_invalid_blocks() {
+        let mut points = Vec::new();
+        let rect = BlockBuilder::rect(0.0, 0.0).points(vec![0.0, 0.0, 2.0, 2.0], &mut points).build();
+        let no_shape = Block { shape: ShapeKind::None, ..rect };
+        let no_points = Block { index: 5, ..rect };
+        let circle = Block { shape: ShapeKind::Circle, ..rect };
+        assert!(draw_shapes(&[no_shape, no_points, circle], &points).is_empty());
+
+        // called directly, a NaN vertex skips validation and must not panic the scanline sort
+        let mut pixels = PixelBuffer::new();
+        fill_polygon(&[0.0, 0.0, f32::NAN, 2.0, 4.0, 4.0, 0.0, 4.0], &rect, &mut pixels, RED);
+    }
+
+    #[test]
+    fn test_pixel_grid() {
+        let mut grid = PixelGrid::new(4, 3);
+        assert!(grid.set(3, 2, RED));
+        assert!(!grid.set(4, 0, RED));
+        assert!(!grid.set(-1, 0, RED));
+        grid.set_pixel(-0.0, 0.0, GREEN);
+        assert_eq!(grid.get(0, 0), Some(GREEN));
+        assert_eq!(grid.get_color(0.0, 0.0), Some(GREEN));
+        assert_eq!(grid.get(3, 2), Some(RED));
+        assert_eq!(grid.get(9, 9), None);
+        let set: Vec<_> = grid.iter().map(|p| (p.point.x, p.point.y)).collect();
+        assert_eq!(set, vec![(0.0, 0.0), (3.0, 2.0)]);
+        assert_eq!(grid.to_pixel_map().len(), 2);
+        grid.unset(0, 0);
+        assert_eq!(grid.iter().count(), 1);
+        grid.clear();
+        assert_eq!(grid.iter().count(), 0);
+    }
+
+    #[test]
+    fn test_far_lines_are_clipped() {
+        // stepping one pixel at a time from 1e30 would never finish
+        let mut grid = PixelGrid::new(10, 10);
+        draw_line(-1e30, 2.0, 1e30, 2.0, &mut grid, RED);
+        draw_line(-1e6, -1e6, 1e6, 1e6, &mut grid, GREEN);
+        draw_line(3.0, -1e30, 3.5, 1e30, &mut grid, GREEN);
+        assert_eq!(grid.get(0, 2), Some(RED));
+        assert_eq!(grid.get(9, 9), Some(GREEN));
+        assert_eq!(grid.get(3, 0), Some(GREEN));
+        draw_line(20.0, 20.0, 1e30, 1e29, &mut grid, RED);
+        draw_line(-1e30, -1e30, 1e30, 1e30, &mut grid, RED);
+
+        let mut buffer = PixelBuffer::new();
+        draw_line(0.0, 0.0, 1e30, 1.0, &mut buffer, RED);
+        assert_eq!(buffer.get(1000, 0), Some(RED));
+    }
+
+    #[test]
+    fn test_pixel_buffer() {
+        let mut buffer = PixelBuffer::new();
+        buffer.set_pixel(-0.0, 0.0, GREEN);
+        buffer.set_pixel(-3.0, 2.4, RED);
+        assert_eq!(buffer.get(0, 0), Some(GREEN));
+        assert_eq!(buffer.get_color(-3.0, 2.0), Some(RED));
+        assert_eq!(buffer.len(), 2);
+
+        // -0 and 0 are the same pixel in a `PixelMap` as well
+        let mut map = PixelMap::new();
+        set_pixel(&mut map, -0.0, 0.0, RED);
+        set_pixel(&mut map, 0.0, -0.0, GREEN);
+        assert_eq!(map.len(), 1);
+        let converted = PixelBuffer::from(&buffer.to_pixel_map());
+        assert_eq!(converted, buffer);
+        buffer.unset(0, 0);
+        assert_eq!(buffer.get(0, 0), None);
+        buffer.clear();
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn test_pixel_grid_matches_pixel_map() {
+        let game = TestGame::new(GameConfig::default());
+        let mut grid = PixelGrid::from_model(&game);
+        assert_eq!((grid.width(), grid.height()), (80, 80));
+        grid.set(1, 1, RED);
+        grid.reset_to_model(&game);
+        assert_eq!(grid.get(1, 1), None);
+        let mut game = game;
+        game.config.map_size *= 2;
+        grid.reset_to_model(&game);
+        assert_eq!((grid.width(), grid.height()), (160, 160));
+        let (blocks, points) = parse_shapes(include_str!("../../game_cfg")).unwrap();
+        let mut grid = PixelGrid::new(100, 100);
+        draw_shapes_into(&blocks, &points, &mut grid);
+        assert_eq!(to_snapshot(&grid), to_snapshot(&draw_shapes(&blocks, &points)));
+    }
+}
@@ -0,0 +1,298 @@
+//! # Game Config
+//! Reads the `key=value` game configuration, see `game.config`
+//!
+//! ```text
+//! width=800
+//! height=800
+//! map_size=80
+//! board_width=600
+//! board_height=600
+//! scale_mode=stretch
+//! grid=true
+//! grid_color=0.3, 0.3, 0.3, 0.3
+//! grid_spacing=2
+//! grid_major_every=0
+//! grid_major_color=0.5, 0.5, 0.5, 0.5
+//! ```
+//!
+//! Missing keys fall back to the defaults, blank lines and lines starting with `#` are ignored.
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+use graphics::types::Color;
+use crate::game_engine::background::Background;
+use crate::game_engine::game_data_model::GameDataModel;
+use crate::game_engine::grid::GridOptions;
+use crate::game_engine::layout::ScaleMode;
+use crate::game_engine::shapes::{Block, Shape};
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct GameConfig {
+    pub width: i32,
+    pub height: i32,
+    pub map_size: i32,
+    pub board_width: f32,
+    pub board_height: f32,
+    /// `fixed`, `stretch` or `letterbox`
+    pub scale_mode: ScaleMode,
+    pub grid: GridOptions,
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(io::Error),
+    /// line is not in `key=value` form
+    Syntax { line: usize, text: String },
+    UnknownKey { line: usize, key: String },
+    InvalidValue { line: usize, key: String, value: String },
+    /// values parsed fine, but don't make sense together
+    Invalid(String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(e) => write!(f, "failed to read config: {}", e),
+            ConfigError::Syntax { line, text } =>
+                write!(f, "line {}: expected `key=value`, found `{}`", line, text),
+            ConfigError::UnknownKey { line, key } => write!(f, "line {}: unknown key `{}`", line, key),
+            ConfigError::InvalidValue { line, key, value } =>
+                write!(f, "line {}: invalid value `{}` for `{}`", line, value, key),
+            ConfigError::Invalid(reason) => write!(f, "invalid config: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ConfigError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for ConfigError {
+    fn from(e: io::Error) -> Self {
+        ConfigError::Io(e)
+    }
+}
+
+impl Default for GameConfig {
+    fn default() -> Self {
+        GameConfig {
+            width: 800,
+            height: 800,
+            map_size: 80,
+            board_width: 600.0,
+            board_height: 600.0,
+            scale_mode: ScaleMode::Stretch,
+            grid: GridOptions::default(),
+        }
+    }
+}
+
+impl GameConfig {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<GameConfig, ConfigError> {
+        GameConfig::parse(&fs::read_to_string(path)?)
+    }
+
+    pub fn parse(source: &str) -> Result<GameConfig, ConfigError> {
+        let mut config = GameConfig::default();
+        for (line, key, value) in parse_key_values(source)? {
+            match key {
+                "width" => config.width = parse_value(line, key, value)?,
+                "height" => config.height = parse_value(line, key, value)?,
+                "map_size" => config.map_size = parse_value(line, key, value)?,
+                "board_width" => config.board_width = parse_value(line, key, value)?,
+                "board_height" => config.board_height = parse_value(line, key, value)?,
+                "scale_mode" => config.scale_mode = ScaleMode::from_name(value).ok_or_else(|| {
+                    ConfigError::InvalidValue { line, key: key.to_string(), value: value.to_string() }
+                })?,
+                "grid" => config.grid.enabled = parse_value(line, key, value)?,
+                "grid_color" => config.grid.color = parse_color(line, key, value)?,
+                "grid_spacing" => config.grid.spacing = parse_value(line, key, value)?,
+                "grid_major_every" => config.grid.major_every = parse_value(line, key, value)?,
+                "grid_major_color" => config.grid.major_color = parse_color(line, key, value)?,
+                _ => return Err(ConfigError::UnknownKey { line, key: key.to_string() }),
+            }
+        }
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.width <= 0 || self.height <= 0 {
+            return Err(ConfigError::Invalid(format!(
+                "window size {}x{} must be positive", self.width, self.height
+            )));
+        }
+        if self.map_size <= 0 {
+            return Err(ConfigError::Invalid(format!("map_size {} must be positive", self.map_size)));
+        }
+        // NaN fails every comparison, so check for the valid range
+        let positive = |v: f32| v.is_finite() && v > 0.0;
+        if !positive(self.board_width) || !positive(self.board_height) {
+            return Err(ConfigError::Invalid(format!(
+                "board size {}x{} must be positive", self.board_width, self.board_height
+            )));
+        }
+        if self.board_width > self.width as f32 || self.board_height > self.height as f32 {
+            return Err(ConfigError::Invalid(format!(
+                "board size {}x{} does not fit in window size {}x{}",
+                self.board_width, self.board_height, self.width, self.height
+            )));
+        }
+        Ok(())
+    }
+
+    /// width of a single block, board is divided in `map_size` blocks
+    pub fn block_width(&self) -> f32 {
+        self.board_width / self.map_size as f32
+    }
+
+    pub fn block_height(&self) -> f32 {
+        self.board_height / self.map_size as f32
+    }
+}
+
+/// splits `key=value` lines, returns line number, key and value for each entry
+pub(crate) fn parse_key_values(source: &str) -> Result<Vec<(usize, &str, &str)>, ConfigError> {
+    let mut entries = Vec::new();
+    for (index, raw_line) in source.lines().enumerate() {
+        let line = raw_line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match line.split_once('=') {
+            Some((key, value)) if !key.trim().is_empty() => {
+                entries.push((index + 1, key.trim(), value.trim()))
+            }
+            _ => return Err(ConfigError::Syntax { line: index + 1, text: line.to_string() }),
+        }
+    }
+    Ok(entries)
+}
+
+fn parse_value<T: FromStr>(line: usize, key: &str, value: &str) -> Result<T, ConfigError> {
+    value.parse().map_err(|_| ConfigError::InvalidValue {
+        line,
+        key: key.to_string(),
+        value: value.to_string(),
+    })
+}
+
+/// `r, g, b, a` with every value in 0.0..=1.0
+fn parse_color(line: usize, key: &str, value: &str) -> Result<Color, ConfigError> {
+    let values: Vec<f32> = value.split(',')
+        .map(|v| parse_value(line, key, v.trim()))
+        .collect::<Result<_, _>>()?;
+    match values.as_slice() {
+        &[r, g, b, a] if values.iter().all(|v| (0.0..=1.0).contains(v)) => Ok([r, g, b, a]),
+        _ => Err(ConfigError::InvalidValue { line, key: key.to_string(), value: value.to_string() }),
+    }
+}
+
+/// Games embedding a `GameConfig` implement this instead of `GameDataModel`,
+/// window, board and block sizes are then taken from the config
+pub trait ConfiguredGame {
+    fn get_config(&self) -> &GameConfig;
+    fn get_drawables(&self) -> (Vec<Block>, &Vec<Shape>);
+    fn get_background(&self) -> Background {
+        Background::default()
+    }
+}
+
+impl<T: ConfiguredGame> GameDataModel for T {
+    fn get_drawables(&self) -> (Vec<Block>, &Vec<Shape>) {
+        ConfiguredGame::get_drawables(self)
+    }
+
+    fn get_window_width(&self) -> i32 {
+        self.get_config().width
+    }
+
+    fn get_window_height(&self) -> i32 {
+        self.get_config().height
+    }
+
+    fn get_map_size(&self) -> i32 {
+        self.get_config().map_size
+    }
+
+    fn get_board_width(&self) -> f32 {
+        self.get_config().board_width
+    }
+
+    fn get_board_height(&self) -> f32 {
+        self.get_config().board_height
+    }
+
+    fn get_block_width(&self) -> f32 {
+        self.get_config().block_width()
+    }
+
+    fn get_block_height(&self) -> f32 {
+        self.get_config().block_height()
+    }
+
+    fn get_scale_mode(&self) -> ScaleMode {
+        self.get_config().scale_mode
+    }
+
+    fn get_grid_options(&self) -> GridOptions {
+        self.get_config().grid
+    }
+
+    fn get_background(&self) -> Background {
+        ConfiguredGame::get_background(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game_engine::game_config::{GameConfig, ConfigError};
+    use crate::game_engine::test_support::TestGame;
+    use crate::game_engine::game_data_model::GameDataModel;
+
+    #[test]
+    fn test_parse_game_config() {
+        let config = GameConfig::parse(include_str!("../../game.config")).unwrap();
+        assert_eq!(config, GameConfig::default());
+        assert_eq!(config.block_width(), 7.5);
+        assert_eq!(config.block_height(), 7.5);
+    }
+
+    #[test]
+    fn test_defaults_and_errors() {
+        let config = GameConfig::parse("# small board\nmap_size = 10\n").unwrap();
+        assert_eq!(config.map_size, 10);
+        assert_eq!(config.width, 800);
+        assert!(matches!(GameConfig::parse("width=800\nspeed=2"),
+            Err(ConfigError::UnknownKey { line: 2, .. })));
+        assert!(matches!(GameConfig::parse("width=wide"), Err(ConfigError::InvalidValue { line: 1, .. })));
+        assert!(matches!(GameConfig::parse("width"), Err(ConfigError::Syntax { line: 1, .. })));
+        assert!(matches!(GameConfig::parse("board_width=900"), Err(ConfigError::Invalid(_))));
+        assert!(matches!(GameConfig::parse("map_size=0"), Err(ConfigError::Invalid(_))));
+        assert!(matches!(GameConfig::parse("board_width=NaN"), Err(ConfigError::Invalid(_))));
+        assert!(matches!(GameConfig::parse("board_height=NaN"), Err(ConfigError::Invalid(_))));
+        assert!(matches!(GameConfig::parse("scale_mode=zoom"), Err(ConfigError::InvalidValue { line: 1, .. })));
+        let config = GameConfig::parse("grid=false\ngrid_color=1, 0, 0, 0.5\ngrid_major_every=5").unwrap();
+        assert_eq!((config.grid.enabled, config.grid.color, config.grid.major_every), (false, [1.0, 0.0, 0.0, 0.5], 5));
+        assert!(matches!(GameConfig::parse("grid_color=1, 0, 0"), Err(ConfigError::InvalidValue { line: 1, .. })));
+        assert!(matches!(GameConfig::parse("grid_color=2, 0, 0, 1"), Err(ConfigError::InvalidValue { line: 1, .. })));
+    }
+
+    #[test]
+    fn test_configured_game_data_model() {
+        let config = GameConfig::parse("board_width=400\nboard_height=200\nmap_size=40").unwrap();
+        let game = TestGame::new(config);
+        assert_eq!(game.get_window_width(), 800);
+        assert_eq!(game.get_board_width(), 400.0);
+        assert_eq!(game.get_block_width(), 10.0);
+        assert_eq!(game.get_block_height(), 5.0);
+    }
+}
@@ -1,10 +1,12 @@
-use crate::game_engine::shapes::Block;
-use crate::game_engine::game_board::PixelMap;
+use crate::game_engine::background::Background;
+use crate::game_engine::grid::GridOptions;
+use crate::game_engine::layout::ScaleMode;
+use crate::game_engine::shapes::{Block, Shape};
 
 /// GameDataModel implements engine independent game data
 /// It should never bound to any specific game library
 pub trait GameDataModel {
-    fn get_drawables(&self) -> (Vec<Block>, &Vec<Vec<f32>>);
+    fn get_drawables(&self) -> (Vec<Block>, &Vec<Shape>);
     fn get_window_width(&self) -> i32;
     fn get_window_height(&self) -> i32;
     fn get_map_size(&self) -> i32;
@@ -12,4 +14,15 @@ pub trait GameDataModel {
     fn get_board_height(&self) -> f32;
     fn get_block_width(&self) -> f32;
     fn get_block_height(&self) -> f32;
+    /// how the board follows the window when it is resized
+    fn get_scale_mode(&self) -> ScaleMode {
+        ScaleMode::Stretch
+    }
+    fn get_grid_options(&self) -> GridOptions {
+        GridOptions::default()
+    }
+    /// drawn before the grid and pixels, black by default
+    fn get_background(&self) -> Background {
+        Background::default()
+    }
 }
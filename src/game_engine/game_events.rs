@@ -1,10 +1,20 @@
-use piston::{RenderArgs, UpdateArgs, Event, Button, Key, MouseButton, ControllerButton, HatState, ControllerHat};
-use crate::game_engine::game_board::PixelMap;
+use crate::game_engine::backend::{RenderArgs, UpdateArgs};
+use crate::game_engine::input::{Button, InputState, MouseEvent};
+use crate::game_engine::game_board::PixelGrid;
+use crate::game_engine::layout::BoardLayout;
 
 pub trait PistonGameEvents {
-    fn update_game_board(&mut self, args: &RenderArgs) -> PixelMap;
-    fn update(&mut self, args: &UpdateArgs);
+    /// draws the frame into `pixels`, one cell per block on the board, cleared before every frame,
+    /// e.g. with `draw_shapes_into`
+    fn update_game_board(&mut self, args: &RenderArgs, pixels: &mut PixelGrid);
+    /// `input` has the keys and buttons held, and the ones pressed or released since the last update
+    fn update(&mut self, args: &UpdateArgs, input: &InputState);
     fn handle_press_events(&mut self, button: &Button);
     fn handle_release_events(&mut self, button: &Button);
+    /// cursor moves, clicks, drags and scrolling, with the board cell under the cursor
+    fn handle_mouse_events(&mut self, _event: &MouseEvent) {}
+    /// window was resized, `layout` has the new block size and board position,
+    /// also called once the backend first reports the window size
+    fn on_resize(&mut self, _layout: &BoardLayout) {}
     // events
 }
@@ -0,0 +1,272 @@
+//! # Geometry
+//! Shapes registered once and shared by many blocks, e.g. all enemies using the same polygon.
+//! Every block instance holding a shape counts as a reference, the slot is reused once nobody holds it.
+
+use std::collections::{HashMap, HashSet};
+use crate::game_engine::shapes::{Block, BlockBuilder, BlockError, Shape};
+
+/// Refers to a registered shape, stays invalid once the shape is released
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct GeometryHandle {
+    index: usize,
+    generation: u32,
+}
+
+impl GeometryHandle {
+    /// index into `GeometryRegistry::shapes`, what `Block::index` is set to
+    pub fn index(&self) -> usize {
+        self.index
+    }
+}
+
+/// The reference a block built with `GeometryRegistry::instance` holds,
+/// releasing it more than once has no effect
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct GeometryInstance {
+    id: u64,
+    handle: GeometryHandle,
+}
+
+impl GeometryInstance {
+    pub fn handle(&self) -> GeometryHandle {
+        self.handle
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+struct Slot {
+    generation: u32,
+    /// references held through the handle, from `register` and `retain`
+    refs: usize,
+    /// blocks built with `instance` and not released yet
+    instances: usize,
+    name: Option<String>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct GeometryRegistry {
+    /// free slots keep their last shape until reused, no block refers to them
+    shapes: Vec<Shape>,
+    slots: Vec<Slot>,
+    free: Vec<usize>,
+    names: HashMap<String, GeometryHandle>,
+    /// instances not released yet, ids are never reused
+    instances: HashSet<u64>,
+    next_instance: u64,
+}
+
+impl GeometryRegistry {
+    pub fn new() -> Self {
+        GeometryRegistry::default()
+    }
+
+    /// adds the shape with one reference, held by the caller
+    pub fn register(&mut self, shape: Shape) -> Result<GeometryHandle, BlockError> {
+        shape.validate()?;
+        let index = match self.free.pop() {
+            Some(index) => {
+                self.shapes[index] = shape;
+                index
+            }
+            None => {
+                self.shapes.push(shape);
+                self.slots.push(Slot::default());
+                self.shapes.len() - 1
+            }
+        };
+        let slot = &mut self.slots[index];
+        slot.refs = 1;
+        slot.instances = 0;
+        Ok(GeometryHandle { index, generation: slot.generation })
+    }
+
+    /// like `register`, the shape can be found by name afterwards,
+    /// a shape registered earlier under the same name loses the name
+    pub fn register_named(&mut self, name: &str, shape: Shape) -> Result<GeometryHandle, BlockError> {
+        let handle = self.register(shape)?;
+        if let Some(old) = self.names.insert(name.to_string(), handle) {
+            self.slots[old.index].name = None;
+        }
+        self.slots[handle.index].name = Some(name.to_string());
+        Ok(handle)
+    }
+
+    pub fn find(&self, name: &str) -> Option<GeometryHandle> {
+        self.names.get(name).copied()
+    }
+
+    pub fn get(&self, handle: GeometryHandle) -> Option<&Shape> {
+        self.slot(handle).map(|_| &self.shapes[handle.index])
+    }
+
+    /// number of references, 0 for released handles
+    pub fn ref_count(&self, handle: GeometryHandle) -> usize {
+        self.slot(handle).map_or(0, |s| s.refs + s.instances)
+    }
+
+    /// adds a reference, returns false for released handles
+    pub fn retain(&mut self, handle: GeometryHandle) -> bool {
+        match self.slot_mut(handle) {
+            Some(slot) => {
+                slot.refs += 1;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// drops a reference taken with `register` or `retain`, returns true if it was the last one
+    /// and the shape was removed, releasing more often than that has no effect
+    pub fn release(&mut self, handle: GeometryHandle) -> bool {
+        match self.slot_mut(handle) {
+            Some(slot) if slot.refs > 0 => slot.refs -= 1,
+            _ => return false,
+        }
+        self.remove_unused(handle.index)
+    }
+
+    /// drops the reference of a block built with `instance`, returns true if it was the last one,
+    /// instances released before are ignored
+    pub fn release_instance(&mut self, instance: GeometryInstance) -> bool {
+        if !self.instances.remove(&instance.id) {
+            return false;
+        }
+        match self.slot_mut(instance.handle) {
+            Some(slot) => slot.instances -= 1,
+            None => return false,
+        }
+        self.remove_unused(instance.handle.index)
+    }
+
+    /// frees the slot once nobody holds it
+    fn remove_unused(&mut self, index: usize) -> bool {
+        let slot = &mut self.slots[index];
+        if slot.refs + slot.instances > 0 {
+            return false;
+        }
+        slot.generation = slot.generation.wrapping_add(1);
+        if let Some(name) = slot.name.take() {
+            self.names.remove(&name);
+        }
+        self.free.push(index);
+        true
+    }
+
+    /// builds a block using the registered shape, the block holds a reference until `release_instance`
+    pub fn instance(&mut self, handle: GeometryHandle, builder: &mut BlockBuilder) -> Result<(Block, GeometryInstance), BlockError> {
+        if self.slot(handle).is_none() {
+            return Err(BlockError::MissingPoints);
+        }
+        let block = builder.shared_geometry(handle.index, &self.shapes).try_build()?;
+        self.slots[handle.index].instances += 1;
+        let instance = GeometryInstance { id: self.next_instance, handle };
+        self.next_instance += 1;
+        self.instances.insert(instance.id);
+        Ok((block, instance))
+    }
+
+    /// number of registered shapes
+    pub fn len(&self) -> usize {
+        self.slots.len() - self.free.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// the geometry list blocks index into, pass it to `draw_shapes` or return it from `get_drawables`
+    pub fn shapes(&self) -> &Vec<Shape> {
+        &self.shapes
+    }
+
+    fn slot(&self, handle: GeometryHandle) -> Option<&Slot> {
+        self.slots.get(handle.index).filter(|s| s.generation == handle.generation && s.refs + s.instances > 0)
+    }
+
+    fn slot_mut(&mut self, handle: GeometryHandle) -> Option<&mut Slot> {
+        self.slots.get_mut(handle.index).filter(|s| s.generation == handle.generation && s.refs + s.instances > 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game_engine::draw_shapes;
+    use crate::game_engine::game_board::PixelStore;
+    use crate::game_engine::geometry::GeometryRegistry;
+    use crate::game_engine::shapes::{BlockBuilder, BlockError, Point, Shape, ShapeKind};
+
+    fn triangle() -> Shape {
+        Shape::Polygon(vec![Point { x: 0.0, y: 0.0 }, Point { x: 4.0, y: 0.0 }, Point { x: 0.0, y: 4.0 }])
+    }
+
+    #[test]
+    fn test_instancing() {
+        let mut registry = GeometryRegistry::new();
+        let enemy = registry.register_named("enemy", triangle()).unwrap();
+        let blocks: Vec<_> = (0..10)
+            .map(|i| registry.instance(enemy, &mut BlockBuilder::polygon(i as f32 * 10.0, 0.0)).unwrap().0)
+            .collect();
+        assert_eq!(registry.shapes().len(), 1);
+        assert_eq!(registry.ref_count(enemy), 11);
+        assert_eq!(registry.find("enemy"), Some(enemy));
+        assert_eq!(draw_shapes(&blocks, registry.shapes()).get_color(90.0, 0.0), Some([1.0, 1.0, 1.0, 1.0]));
+        assert_eq!(
+            registry.instance(enemy, &mut BlockBuilder::circle(0.0, 0.0)).unwrap_err(),
+            BlockError::ShapeMismatch { expected: ShapeKind::Circle, found: ShapeKind::Polygon }
+        );
+    }
+
+    #[test]
+    fn test_release_reuses_slots() {
+        let mut registry = GeometryRegistry::new();
+        let enemy = registry.register_named("enemy", triangle()).unwrap();
+        let (_, instance) = registry.instance(enemy, &mut BlockBuilder::polygon(0.0, 0.0)).unwrap();
+        assert!(!registry.release(enemy));
+        assert!(registry.release_instance(instance));
+        assert!(registry.is_empty());
+        assert_eq!(registry.find("enemy"), None);
+        assert_eq!(registry.get(enemy), None);
+        // the slot is reused, the old handle stays invalid
+        let circle = registry.register(Shape::Circle { center: Point { x: 0.0, y: 0.0 }, radius: 2.0 }).unwrap();
+        assert_eq!(circle.index(), enemy.index());
+        assert_eq!(registry.shapes().len(), 1);
+        assert!(!registry.retain(enemy));
+        assert!(!registry.release(enemy));
+        assert_eq!(registry.ref_count(circle), 1);
+    }
+
+    #[test]
+    fn test_release_owner_twice() {
+        let mut registry = GeometryRegistry::new();
+        let enemy = registry.register(triangle()).unwrap();
+        let (_, instance) = registry.instance(enemy, &mut BlockBuilder::polygon(0.0, 0.0)).unwrap();
+        assert!(!registry.release(enemy));
+        // the handle is copied easily, releasing it again must not drop the instance's reference
+        assert!(!registry.release(enemy));
+        assert_eq!(registry.ref_count(enemy), 1);
+        assert!(registry.get(enemy).is_some());
+        let circle = registry.register(Shape::Circle { center: Point { x: 0.0, y: 0.0 }, radius: 2.0 }).unwrap();
+        assert_ne!(circle.index(), enemy.index());
+        assert!(registry.release_instance(instance));
+        assert_eq!(registry.get(enemy), None);
+    }
+
+    #[test]
+    fn test_release_instance_twice() {
+        let mut registry = GeometryRegistry::new();
+        let enemy = registry.register(triangle()).unwrap();
+        let (_, instance) = registry.instance(enemy, &mut BlockBuilder::polygon(0.0, 0.0)).unwrap();
+        let copy = instance;
+        assert!(!registry.release_instance(instance));
+        assert!(!registry.release_instance(copy));
+        // the caller still holds `enemy`
+        assert_eq!(registry.ref_count(enemy), 1);
+        assert!(registry.release(enemy));
+
+        // a stale instance does not release the shape reusing the slot
+        let circle = registry.register(Shape::Circle { center: Point { x: 0.0, y: 0.0 }, radius: 2.0 }).unwrap();
+        assert_eq!(circle.index(), enemy.index());
+        assert!(!registry.release_instance(instance));
+        assert_eq!(registry.ref_count(circle), 1);
+    }
+}
@@ -0,0 +1,96 @@
+//! # Grid
+//! Grid lines drawn over the board, shared by all renderers
+
+use graphics::types::Color;
+
+pub const GRID_COLOR: Color = [0.3, 0.3, 0.3, 0.3];
+pub const MAJOR_GRID_COLOR: Color = [0.5, 0.5, 0.5, 0.5];
+
+/// How the grid over the board is drawn
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct GridOptions {
+    pub enabled: bool,
+    pub color: Color,
+    /// blocks between two lines
+    pub spacing: u32,
+    /// every n-th line is a major line, 0 for no major lines
+    pub major_every: u32,
+    pub major_color: Color,
+}
+
+impl Default for GridOptions {
+    fn default() -> Self {
+        GridOptions {
+            enabled: true,
+            color: GRID_COLOR,
+            spacing: 2,
+            major_every: 0,
+            major_color: MAJOR_GRID_COLOR,
+        }
+    }
+}
+
+impl GridOptions {
+    pub fn disabled() -> Self {
+        GridOptions { enabled: false, ..GridOptions::default() }
+    }
+}
+
+/// A grid line from `(x1, y1)` to `(x2, y2)` in board coordinates
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct GridLine {
+    pub points: [f64; 4],
+    pub color: Color,
+}
+
+/// vertical lines followed by horizontal lines, inside the board only
+pub fn grid_lines(board_width: f32, board_height: f32, block_width: f32, block_height: f32, options: &GridOptions) -> Vec<GridLine> {
+    let mut lines = Vec::new();
+    if !options.enabled || options.spacing == 0 || block_width <= 0.0 || block_height <= 0.0 {
+        return lines;
+    }
+    let color = |n: u32| {
+        if options.major_every > 0 && n.is_multiple_of(options.major_every) {
+            options.major_color
+        } else {
+            options.color
+        }
+    };
+    let (board_width, board_height) = (board_width as f64, board_height as f64);
+    let step = options.spacing as f64 * block_width as f64;
+    let mut n = 1;
+    while n as f64 * step < board_width {
+        let x = n as f64 * step;
+        lines.push(GridLine { points: [x, 0.0, x, board_height], color: color(n) });
+        n += 1;
+    }
+    let step = options.spacing as f64 * block_height as f64;
+    let mut n = 1;
+    while n as f64 * step < board_height {
+        let y = n as f64 * step;
+        lines.push(GridLine { points: [0.0, y, board_width, y], color: color(n) });
+        n += 1;
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game_engine::grid::{grid_lines, GridOptions, GRID_COLOR, MAJOR_GRID_COLOR};
+
+    #[test]
+    fn test_grid_lines() {
+        let lines = grid_lines(40.0, 20.0, 5.0, 5.0, &GridOptions::default());
+        // 3 vertical, 1 horizontal spanning the whole width
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0].points, [10.0, 0.0, 10.0, 20.0]);
+        assert_eq!(lines[3].points, [0.0, 10.0, 40.0, 10.0]);
+
+        let options = GridOptions { spacing: 1, major_every: 4, ..GridOptions::default() };
+        let lines = grid_lines(40.0, 20.0, 5.0, 5.0, &options);
+        assert_eq!(lines.len(), 7 + 3);
+        assert_eq!(lines[3].color, MAJOR_GRID_COLOR);
+        assert_eq!(lines[4].color, GRID_COLOR);
+        assert!(grid_lines(40.0, 20.0, 5.0, 5.0, &GridOptions::disabled()).is_empty());
+    }
+}
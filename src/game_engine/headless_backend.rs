@@ -0,0 +1,203 @@
+//! # Headless Backend
+//! Renders frames in memory with the software renderer, events are queued up front.
+//! Useful for tests and for running games on machines without a GPU.
+//! Only the last frame is kept unless `keep_frames` asks for more.
+
+use std::collections::VecDeque;
+use image::RgbaImage;
+use crate::game_engine::background::{Background, ImageCache};
+use crate::game_engine::backend::{Backend, BackendEvent, Frame, RenderArgs, UpdateArgs};
+use crate::game_engine::software_renderer::{load_background, render_frame_onto};
+
+pub struct HeadlessBackend {
+    width: u32,
+    height: u32,
+    events: VecDeque<BackendEvent>,
+    frames: Vec<RgbaImage>,
+    max_frames: usize,
+    background: ImageCache<RgbaImage>,
+}
+
+impl HeadlessBackend {
+    pub fn new(width: u32, height: u32) -> Self {
+        HeadlessBackend {
+            width,
+            height,
+            events: VecDeque::new(),
+            frames: Vec::new(),
+            max_frames: 1,
+            background: ImageCache::new(),
+        }
+    }
+
+    /// number of presented frames to keep, at least 1, older ones are dropped
+    pub fn keep_frames(&mut self, count: usize) -> &mut Self {
+        self.max_frames = count.max(1);
+        self
+    }
+
+    /// the loop stops once all queued events are handled,
+    /// `Resize` changes the size of the frames rendered after it
+    pub fn push_event(&mut self, event: BackendEvent) -> &mut Self {
+        self.events.push_back(event);
+        self
+    }
+
+    /// queues an update of `dt` seconds followed by a render
+    pub fn push_frame(&mut self, dt: f64) -> &mut Self {
+        self.push_event(BackendEvent::Update(UpdateArgs { dt }));
+        let args = self.render_args();
+        self.push_event(BackendEvent::Render(args))
+    }
+
+    pub fn render_args(&self) -> RenderArgs {
+        RenderArgs {
+            ext_dt: 0.0,
+            alpha: 0.0,
+            window_size: [self.width as f64, self.height as f64],
+            draw_size: [self.width, self.height],
+        }
+    }
+
+    /// the kept frames, oldest first
+    pub fn frames(&self) -> &[RgbaImage] {
+        &self.frames
+    }
+
+    pub fn last_frame(&self) -> Option<&RgbaImage> {
+        self.frames.last()
+    }
+}
+
+impl Backend for HeadlessBackend {
+    fn poll_event(&mut self) -> Option<BackendEvent> {
+        match self.events.pop_front()? {
+            BackendEvent::Resize { draw_size, window_size } => {
+                self.width = draw_size[0];
+                self.height = draw_size[1];
+                Some(BackendEvent::Resize { draw_size, window_size })
+            }
+            // renders queued before a resize still get the current size
+            BackendEvent::Render(args) => Some(BackendEvent::Render(RenderArgs {
+                window_size: [self.width as f64, self.height as f64],
+                draw_size: [self.width, self.height],
+                ..args
+            })),
+            event => Some(event),
+        }
+    }
+
+    fn present(&mut self, frame: &Frame, _args: &RenderArgs) {
+        // without a background the new frame is drawn over the last one, unless the size changed
+        let mut image = match self.frames.last() {
+            Some(last) if last.dimensions() == (self.width, self.height) => last.clone(),
+            _ => RgbaImage::from_pixel(self.width, self.height, image::Rgba([0, 0, 0, 255])),
+        };
+        let background = match &frame.background {
+            Background::Image(path) => self.background.get_or_load(path, load_background),
+            _ => None,
+        };
+        render_frame_onto(&mut image, frame, background);
+        self.frames.push(image);
+        if self.frames.len() > self.max_frames {
+            let excess = self.frames.len() - self.max_frames;
+            self.frames.drain(..excess);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::Rgba;
+    use crate::RED;
+    use crate::game_engine::backend::{BackendEvent, RenderArgs, UpdateArgs};
+    use crate::game_engine::background::Background;
+    use crate::game_engine::game_board::{set_pixel, PixelGrid};
+    use crate::game_engine::game_config::GameConfig;
+    use crate::game_engine::test_support::TestGame;
+    use crate::game_engine::game_events::PistonGameEvents;
+    use crate::game_engine::headless_backend::HeadlessBackend;
+    use crate::game_engine::input::{Button, InputEvent, InputState, Key};
+    use crate::game_engine::run_game_loop_with_settings;
+    use crate::game_engine::timestep::LoopSettings;
+
+    #[derive(Default)]
+    struct Player {
+        x: f32,
+        pressed: Vec<Button>,
+        jumps: u32,
+    }
+
+    impl PistonGameEvents for TestGame<Player> {
+        fn update_game_board(&mut self, _args: &RenderArgs, pixels: &mut PixelGrid) {
+            set_pixel(pixels, self.state.x, 0.0, RED);
+        }
+
+        fn update(&mut self, _args: &UpdateArgs, input: &InputState) {
+            self.state.x += 1.0;
+            if input.key_just_pressed(Key::Space) {
+                self.state.jumps += 1;
+            }
+        }
+
+        fn handle_press_events(&mut self, button: &Button) {
+            self.state.pressed.push(*button);
+        }
+
+        fn handle_release_events(&mut self, _button: &Button) {}
+    }
+
+    #[test]
+    fn test_headless_game_loop() {
+        let config = GameConfig::parse("width=20\nheight=20\nmap_size=10\nboard_width=20\nboard_height=20").unwrap();
+        let mut game = TestGame::with_state(config, Player::default());
+        let mut backend = HeadlessBackend::new(20, 20);
+        backend.keep_frames(3)
+            .push_frame(0.1)
+            .push_event(BackendEvent::Input(InputEvent::Press(Button::Keyboard(Key::Space))))
+            .push_frame(0.1)
+            .push_frame(0.1);
+        run_game_loop_with_settings(&mut game, &mut backend, &LoopSettings::new(10.0).unwrap());
+        // only seen as just pressed in the first update after the press
+        assert_eq!(game.state.jumps, 1);
+        assert_eq!(game.state.pressed, vec![Button::Keyboard(Key::Space)]);
+        assert_eq!(backend.frames().len(), 3);
+        // pixel moved one block to the right every frame
+        assert_eq!(*backend.frames()[0].get_pixel(2, 0), Rgba([255, 0, 0, 255]));
+        assert_eq!(*backend.frames()[1].get_pixel(4, 0), Rgba([255, 0, 0, 255]));
+        assert_eq!(*backend.last_frame().unwrap().get_pixel(6, 0), Rgba([255, 0, 0, 255]));
+        assert_eq!(*backend.last_frame().unwrap().get_pixel(2, 0), Rgba([0, 0, 0, 255]));
+    }
+
+    #[test]
+    fn test_headless_resize() {
+        let config = GameConfig::parse("width=20\nheight=20\nmap_size=10\nboard_width=20\nboard_height=20").unwrap();
+        let mut game = TestGame::with_state(config, Player::default());
+        let mut backend = HeadlessBackend::new(20, 20);
+        backend.push_frame(0.1)
+            .push_event(BackendEvent::Resize { window_size: [40.0, 20.0], draw_size: [40, 20] })
+            .push_frame(0.1);
+        run_game_loop_with_settings(&mut game, &mut backend, &LoopSettings::new(10.0).unwrap());
+        // only the last frame is kept, at the new size, with the board stretched to fit
+        assert_eq!(backend.frames().len(), 1);
+        let image = backend.last_frame().unwrap();
+        assert_eq!(image.dimensions(), (40, 20));
+        assert_eq!(*image.get_pixel(8, 0), Rgba([255, 0, 0, 255]));
+        assert_eq!(*image.get_pixel(4, 0), Rgba([0, 0, 0, 255]));
+    }
+
+    #[test]
+    fn test_headless_trail() {
+        let config = GameConfig::parse("width=20\nheight=20\nmap_size=10\nboard_width=20\nboard_height=20\ngrid=false").unwrap();
+        let mut game = TestGame::with_state(config, Player::default());
+        game.background = Background::None;
+        let mut backend = HeadlessBackend::new(20, 20);
+        backend.push_frame(0.1).push_frame(0.1).push_frame(0.1);
+        run_game_loop_with_settings(&mut game, &mut backend, &LoopSettings::new(10.0).unwrap());
+        // without clearing, every position the pixel was at is still there
+        let image = backend.last_frame().unwrap();
+        for x in &[2, 4, 6] {
+            assert_eq!(*image.get_pixel(*x, 0), Rgba([255, 0, 0, 255]));
+        }
+    }
+}
@@ -0,0 +1,560 @@
+//! # Input
+//! Engine owned input events, so game code never depends on the backend's input types.
+
+use std::collections::HashSet;
+
+/// Keyboard keys, keys the engine does not know about are reported as `Other`
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Key {
+    Backspace, Tab, Return, Escape, Space, Quote, Comma, Minus,
+    Period, Slash, D0, D1, D2, D3, D4, D5,
+    D6, D7, D8, D9, Semicolon, Equals, LeftBracket, Backslash,
+    RightBracket, Backquote, A, B, C, D, E, F,
+    G, H, I, J, K, L, M, N,
+    O, P, Q, R, S, T, U, V,
+    W, X, Y, Z, Delete, CapsLock, F1, F2,
+    F3, F4, F5, F6, F7, F8, F9, F10,
+    F11, F12, PrintScreen, ScrollLock, Pause, Insert, Home, PageUp,
+    End, PageDown, Right, Left, Down, Up, NumPad0, NumPad1,
+    NumPad2, NumPad3, NumPad4, NumPad5, NumPad6, NumPad7, NumPad8, NumPad9,
+    NumPadDivide, NumPadMultiply, NumPadMinus, NumPadPlus, NumPadEnter, NumPadPeriod, LCtrl, LShift,
+    LAlt, LGui, RCtrl, RShift, RAlt, RGui,
+    /// backend specific key code
+    Other(i32),
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum MouseButton {
+    Left,
+    Right,
+    Middle,
+    X1,
+    X2,
+    Button6,
+    Button7,
+    Button8,
+    Unknown,
+}
+
+/// Button `button` on gamepad `gamepad`
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct GamepadButton {
+    pub gamepad: u32,
+    pub button: u8,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum HatDirection {
+    Centered,
+    Up,
+    Right,
+    Down,
+    Left,
+    RightUp,
+    RightDown,
+    LeftUp,
+    LeftDown,
+}
+
+/// Hat (d-pad) `hat` on gamepad `gamepad`
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct GamepadHat {
+    pub gamepad: u32,
+    pub hat: u8,
+    pub direction: HatDirection,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Button {
+    Keyboard(Key),
+    Mouse(MouseButton),
+    Gamepad(GamepadButton),
+    Hat(GamepadHat),
+}
+
+impl Key {
+    /// parses key names as written in bindings files, the same as the variant names,
+    /// e.g. `Left`, `A` or `D1`, other key codes are written as `Key:<code>`
+    pub fn from_name(name: &str) -> Option<Key> {
+        if let Some(code) = name.strip_prefix("Key:") {
+            return code.parse().ok().map(Key::Other);
+        }
+        Some(match name {
+            "Backspace" => Key::Backspace,
+            "Tab" => Key::Tab,
+            "Return" => Key::Return,
+            "Escape" => Key::Escape,
+            "Space" => Key::Space,
+            "Quote" => Key::Quote,
+            "Comma" => Key::Comma,
+            "Minus" => Key::Minus,
+            "Period" => Key::Period,
+            "Slash" => Key::Slash,
+            "D0" => Key::D0,
+            "D1" => Key::D1,
+            "D2" => Key::D2,
+            "D3" => Key::D3,
+            "D4" => Key::D4,
+            "D5" => Key::D5,
+            "D6" => Key::D6,
+            "D7" => Key::D7,
+            "D8" => Key::D8,
+            "D9" => Key::D9,
+            "Semicolon" => Key::Semicolon,
+            "Equals" => Key::Equals,
+            "LeftBracket" => Key::LeftBracket,
+            "Backslash" => Key::Backslash,
+            "RightBracket" => Key::RightBracket,
+            "Backquote" => Key::Backquote,
+            "A" => Key::A,
+            "B" => Key::B,
+            "C" => Key::C,
+            "D" => Key::D,
+            "E" => Key::E,
+            "F" => Key::F,
+            "G" => Key::G,
+            "H" => Key::H,
+            "I" => Key::I,
+            "J" => Key::J,
+            "K" => Key::K,
+            "L" => Key::L,
+            "M" => Key::M,
+            "N" => Key::N,
+            "O" => Key::O,
+            "P" => Key::P,
+            "Q" => Key::Q,
+            "R" => Key::R,
+            "S" => Key::S,
+            "T" => Key::T,
+            "U" => Key::U,
+            "V" => Key::V,
+            "W" => Key::W,
+            "X" => Key::X,
+            "Y" => Key::Y,
+            "Z" => Key::Z,
+            "Delete" => Key::Delete,
+            "CapsLock" => Key::CapsLock,
+            "F1" => Key::F1,
+            "F2" => Key::F2,
+            "F3" => Key::F3,
+            "F4" => Key::F4,
+            "F5" => Key::F5,
+            "F6" => Key::F6,
+            "F7" => Key::F7,
+            "F8" => Key::F8,
+            "F9" => Key::F9,
+            "F10" => Key::F10,
+            "F11" => Key::F11,
+            "F12" => Key::F12,
+            "PrintScreen" => Key::PrintScreen,
+            "ScrollLock" => Key::ScrollLock,
+            "Pause" => Key::Pause,
+            "Insert" => Key::Insert,
+            "Home" => Key::Home,
+            "PageUp" => Key::PageUp,
+            "End" => Key::End,
+            "PageDown" => Key::PageDown,
+            "Right" => Key::Right,
+            "Left" => Key::Left,
+            "Down" => Key::Down,
+            "Up" => Key::Up,
+            "NumPad0" => Key::NumPad0,
+            "NumPad1" => Key::NumPad1,
+            "NumPad2" => Key::NumPad2,
+            "NumPad3" => Key::NumPad3,
+            "NumPad4" => Key::NumPad4,
+            "NumPad5" => Key::NumPad5,
+            "NumPad6" => Key::NumPad6,
+            "NumPad7" => Key::NumPad7,
+            "NumPad8" => Key::NumPad8,
+            "NumPad9" => Key::NumPad9,
+            "NumPadDivide" => Key::NumPadDivide,
+            "NumPadMultiply" => Key::NumPadMultiply,
+            "NumPadMinus" => Key::NumPadMinus,
+            "NumPadPlus" => Key::NumPadPlus,
+            "NumPadEnter" => Key::NumPadEnter,
+            "NumPadPeriod" => Key::NumPadPeriod,
+            "LCtrl" => Key::LCtrl,
+            "LShift" => Key::LShift,
+            "LAlt" => Key::LAlt,
+            "LGui" => Key::LGui,
+            "RCtrl" => Key::RCtrl,
+            "RShift" => Key::RShift,
+            "RAlt" => Key::RAlt,
+            "RGui" => Key::RGui,
+            _ => return None,
+        })
+    }
+}
+
+impl MouseButton {
+    pub fn from_name(name: &str) -> Option<MouseButton> {
+        Some(match name {
+            "Left" => MouseButton::Left,
+            "Right" => MouseButton::Right,
+            "Middle" => MouseButton::Middle,
+            "X1" => MouseButton::X1,
+            "X2" => MouseButton::X2,
+            "Button6" => MouseButton::Button6,
+            "Button7" => MouseButton::Button7,
+            "Button8" => MouseButton::Button8,
+            _ => return None,
+        })
+    }
+}
+
+impl HatDirection {
+    pub fn from_name(name: &str) -> Option<HatDirection> {
+        Some(match name {
+            "Centered" => HatDirection::Centered,
+            "Up" => HatDirection::Up,
+            "Right" => HatDirection::Right,
+            "Down" => HatDirection::Down,
+            "Left" => HatDirection::Left,
+            "RightUp" => HatDirection::RightUp,
+            "RightDown" => HatDirection::RightDown,
+            "LeftUp" => HatDirection::LeftUp,
+            "LeftDown" => HatDirection::LeftDown,
+            _ => return None,
+        })
+    }
+}
+
+impl Button {
+    /// parses button names as written in bindings files
+    /// `Space`: keyboard key, see `Key::from_name`
+    /// `Mouse:Left`: mouse button
+    /// `Gamepad:0:3`: button 3 on gamepad 0
+    /// `Hat:0:0:Up`: hat 0 on gamepad 0 pushed up
+    pub fn from_name(name: &str) -> Option<Button> {
+        let parts: Vec<&str> = name.split(':').map(str::trim).collect();
+        match parts.as_slice() {
+            ["Mouse", button] => MouseButton::from_name(button).map(Button::Mouse),
+            ["Gamepad", gamepad, button] => Some(Button::Gamepad(GamepadButton {
+                gamepad: gamepad.parse().ok()?,
+                button: button.parse().ok()?,
+            })),
+            ["Hat", gamepad, hat, direction] => Some(Button::Hat(GamepadHat {
+                gamepad: gamepad.parse().ok()?,
+                hat: hat.parse().ok()?,
+                direction: HatDirection::from_name(direction)?,
+            })),
+            _ => Key::from_name(name).map(Button::Keyboard),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum InputEvent {
+    Press(Button),
+    Release(Button),
+    /// cursor moved, in window coordinates
+    MouseMove([f64; 2]),
+    /// scroll amount, horizontal and vertical
+    MouseScroll([f64; 2]),
+}
+
+/// Cursor position in window coordinates, and the board cell under it
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct CursorPosition {
+    pub window: [f64; 2],
+    /// `None` when the cursor is outside of the board
+    pub cell: Option<[i32; 2]>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum MouseEventKind {
+    Moved,
+    Pressed(MouseButton),
+    Released(MouseButton),
+    /// released where it was pressed, sent after `Released`
+    Clicked(MouseButton),
+    /// moved while the button is held, `from` is where it was pressed
+    Dragged { button: MouseButton, from: CursorPosition },
+    Scrolled([f64; 2]),
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct MouseEvent {
+    pub kind: MouseEventKind,
+    pub position: CursorPosition,
+}
+
+/// board cell under the window position, `None` outside of the board
+pub fn board_cell(position: [f64; 2], block_width: f32, block_height: f32, board_width: f32, board_height: f32) -> Option<[i32; 2]> {
+    let [x, y] = position;
+    if block_width <= 0.0 || block_height <= 0.0 {
+        return None;
+    }
+    if x < 0.0 || y < 0.0 || x >= board_width as f64 || y >= board_height as f64 {
+        return None;
+    }
+    Some([(x / block_width as f64) as i32, (y / block_height as f64) as i32])
+}
+
+/// Turns raw input events into mouse events, keeping track of the cursor and held buttons
+#[derive(Clone, Debug)]
+pub struct MouseTracker {
+    position: CursorPosition,
+    held: Vec<(MouseButton, CursorPosition)>,
+}
+
+impl Default for MouseTracker {
+    fn default() -> Self {
+        MouseTracker {
+            position: CursorPosition { window: [0.0, 0.0], cell: None },
+            held: Vec::new(),
+        }
+    }
+}
+
+impl MouseTracker {
+    pub fn new() -> Self {
+        MouseTracker::default()
+    }
+
+    pub fn position(&self) -> CursorPosition {
+        self.position
+    }
+
+    /// `cell_at` maps a window position to the board cell under it
+    pub fn handle<F: Fn([f64; 2]) -> Option<[i32; 2]>>(&mut self, event: &InputEvent, cell_at: F) -> Vec<MouseEvent> {
+        let mut events = Vec::new();
+        match *event {
+            InputEvent::MouseMove(window) => {
+                self.position = CursorPosition { window, cell: cell_at(window) };
+                events.push(self.event(MouseEventKind::Moved));
+                for &(button, from) in &self.held {
+                    events.push(self.event(MouseEventKind::Dragged { button, from }));
+                }
+            }
+            InputEvent::MouseScroll(amount) => events.push(self.event(MouseEventKind::Scrolled(amount))),
+            InputEvent::Press(Button::Mouse(button)) => {
+                self.held.retain(|(b, _)| *b != button);
+                self.held.push((button, self.position));
+                events.push(self.event(MouseEventKind::Pressed(button)));
+            }
+            InputEvent::Release(Button::Mouse(button)) => {
+                events.push(self.event(MouseEventKind::Released(button)));
+                if let Some(i) = self.held.iter().position(|(b, _)| *b == button) {
+                    let (_, from) = self.held.remove(i);
+                    let same_place = match (from.cell, self.position.cell) {
+                        (Some(a), Some(b)) => a == b,
+                        _ => from.window == self.position.window,
+                    };
+                    if same_place {
+                        events.push(self.event(MouseEventKind::Clicked(button)));
+                    }
+                }
+            }
+            _ => {}
+        }
+        events
+    }
+
+    fn event(&self, kind: MouseEventKind) -> MouseEvent {
+        MouseEvent { kind, position: self.position }
+    }
+}
+
+/// Modifier keys held down, left or right
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Modifiers {
+    pub shift: bool,
+    pub ctrl: bool,
+    pub alt: bool,
+    /// windows / command key
+    pub gui: bool,
+}
+
+/// Input maintained by `game_loop`, to be polled in `PistonGameEvents::update`.
+/// "Just" pressed / released means since the previous update.
+#[derive(Clone, Debug)]
+pub struct InputState {
+    held: HashSet<Button>,
+    pressed: HashSet<Button>,
+    released: HashSet<Button>,
+    cursor: CursorPosition,
+    scroll: [f64; 2],
+}
+
+impl Default for InputState {
+    fn default() -> Self {
+        InputState {
+            held: HashSet::new(),
+            pressed: HashSet::new(),
+            released: HashSet::new(),
+            cursor: CursorPosition { window: [0.0, 0.0], cell: None },
+            scroll: [0.0, 0.0],
+        }
+    }
+}
+
+impl InputState {
+    pub fn new() -> Self {
+        InputState::default()
+    }
+
+    pub fn handle(&mut self, event: &InputEvent) {
+        match *event {
+            InputEvent::Press(button) => {
+                // key repeat sends presses while held, those are not new presses
+                if self.held.insert(button) {
+                    self.pressed.insert(button);
+                }
+            }
+            InputEvent::Release(button) => {
+                if self.held.remove(&button) {
+                    self.released.insert(button);
+                }
+            }
+            InputEvent::MouseScroll([x, y]) => {
+                self.scroll[0] += x;
+                self.scroll[1] += y;
+            }
+            InputEvent::MouseMove(_) => {}
+        }
+    }
+
+    pub fn set_cursor(&mut self, cursor: CursorPosition) {
+        self.cursor = cursor;
+    }
+
+    /// forgets presses, releases and scrolling, called after every update
+    pub fn end_frame(&mut self) {
+        self.pressed.clear();
+        self.released.clear();
+        self.scroll = [0.0, 0.0];
+    }
+
+    pub fn is_held(&self, button: Button) -> bool {
+        self.held.contains(&button)
+    }
+
+    pub fn just_pressed(&self, button: Button) -> bool {
+        self.pressed.contains(&button)
+    }
+
+    pub fn just_released(&self, button: Button) -> bool {
+        self.released.contains(&button)
+    }
+
+    pub fn is_key_held(&self, key: Key) -> bool {
+        self.is_held(Button::Keyboard(key))
+    }
+
+    pub fn key_just_pressed(&self, key: Key) -> bool {
+        self.just_pressed(Button::Keyboard(key))
+    }
+
+    pub fn key_just_released(&self, key: Key) -> bool {
+        self.just_released(Button::Keyboard(key))
+    }
+
+    pub fn is_mouse_held(&self, button: MouseButton) -> bool {
+        self.is_held(Button::Mouse(button))
+    }
+
+    pub fn mouse_just_pressed(&self, button: MouseButton) -> bool {
+        self.just_pressed(Button::Mouse(button))
+    }
+
+    pub fn mouse_just_released(&self, button: MouseButton) -> bool {
+        self.just_released(Button::Mouse(button))
+    }
+
+    /// every button currently held down
+    pub fn held(&self) -> impl Iterator<Item = &Button> {
+        self.held.iter()
+    }
+
+    pub fn cursor(&self) -> CursorPosition {
+        self.cursor
+    }
+
+    /// scrolled since the previous update
+    pub fn scroll(&self) -> [f64; 2] {
+        self.scroll
+    }
+
+    pub fn modifiers(&self) -> Modifiers {
+        let any = |a: Key, b: Key| self.is_key_held(a) || self.is_key_held(b);
+        Modifiers {
+            shift: any(Key::LShift, Key::RShift),
+            ctrl: any(Key::LCtrl, Key::RCtrl),
+            alt: any(Key::LAlt, Key::RAlt),
+            gui: any(Key::LGui, Key::RGui),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game_engine::input::{
+        board_cell, Button, InputEvent, InputState, Key, MouseButton, MouseEvent, MouseEventKind, MouseTracker,
+    };
+
+    #[test]
+    fn test_board_cell() {
+        assert_eq!(board_cell([15.0, 7.4], 7.5, 7.5, 600.0, 600.0), Some([2, 0]));
+        assert_eq!(board_cell([600.0, 10.0], 7.5, 7.5, 600.0, 600.0), None);
+        assert_eq!(board_cell([-1.0, 10.0], 7.5, 7.5, 600.0, 600.0), None);
+    }
+
+    #[test]
+    fn test_mouse_tracker() {
+        let cell_at = |p: [f64; 2]| board_cell(p, 10.0, 10.0, 100.0, 100.0);
+        let mut mouse = MouseTracker::new();
+        let kinds = |events: Vec<MouseEvent>| events.into_iter().map(|e| e.kind).collect::<Vec<_>>();
+        mouse.handle(&InputEvent::MouseMove([12.0, 12.0]), cell_at);
+        assert_eq!(mouse.position().cell, Some([1, 1]));
+        let left = Button::Mouse(MouseButton::Left);
+        assert_eq!(
+            kinds(mouse.handle(&InputEvent::Press(left), cell_at)),
+            vec![MouseEventKind::Pressed(MouseButton::Left)]
+        );
+        // moving inside the same cell still counts as a click
+        let events = mouse.handle(&InputEvent::MouseMove([18.0, 12.0]), cell_at);
+        assert!(matches!(events[1].kind, MouseEventKind::Dragged { button: MouseButton::Left, from } if from.cell == Some([1, 1])));
+        assert_eq!(
+            kinds(mouse.handle(&InputEvent::Release(left), cell_at)),
+            vec![MouseEventKind::Released(MouseButton::Left), MouseEventKind::Clicked(MouseButton::Left)]
+        );
+        // released in another cell is a drag, not a click
+        mouse.handle(&InputEvent::Press(left), cell_at);
+        mouse.handle(&InputEvent::MouseMove([42.0, 12.0]), cell_at);
+        assert_eq!(
+            kinds(mouse.handle(&InputEvent::Release(left), cell_at)),
+            vec![MouseEventKind::Released(MouseButton::Left)]
+        );
+        assert_eq!(kinds(mouse.handle(&InputEvent::MouseMove([50.0, 12.0]), cell_at)), vec![MouseEventKind::Moved]);
+    }
+
+    #[test]
+    fn test_input_state() {
+        let mut input = InputState::new();
+        let left = Button::Keyboard(Key::Left);
+        input.handle(&InputEvent::Press(left));
+        input.handle(&InputEvent::Press(Button::Keyboard(Key::RShift)));
+        input.handle(&InputEvent::MouseScroll([0.0, 2.0]));
+        assert!(input.is_key_held(Key::Left));
+        assert!(input.key_just_pressed(Key::Left));
+        assert!(input.modifiers().shift);
+        assert!(!input.modifiers().ctrl);
+        assert_eq!(input.scroll(), [0.0, 2.0]);
+        input.end_frame();
+        // repeated press while held
+        input.handle(&InputEvent::Press(left));
+        assert!(input.is_held(left));
+        assert!(!input.just_pressed(left));
+        assert_eq!(input.scroll(), [0.0, 0.0]);
+        input.handle(&InputEvent::Release(left));
+        input.handle(&InputEvent::Press(Button::Mouse(MouseButton::Left)));
+        assert!(input.key_just_released(Key::Left));
+        assert!(!input.is_key_held(Key::Left));
+        assert!(input.is_mouse_held(MouseButton::Left));
+        assert!(input.mouse_just_pressed(MouseButton::Left));
+        input.end_frame();
+        assert!(!input.key_just_released(Key::Left));
+    }
+}
@@ -0,0 +1,59 @@
+use std::collections::HashSet;
+
+/// Visibility of block layers, all layers are visible unless hidden
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Layers {
+    hidden: HashSet<i32>,
+}
+
+impl Layers {
+    pub fn new() -> Self {
+        Layers::default()
+    }
+
+    pub fn hide(&mut self, layer: i32) -> &mut Self {
+        self.hidden.insert(layer);
+        self
+    }
+
+    pub fn show(&mut self, layer: i32) -> &mut Self {
+        self.hidden.remove(&layer);
+        self
+    }
+
+    pub fn set_visible(&mut self, layer: i32, visible: bool) -> &mut Self {
+        if visible {
+            self.show(layer)
+        } else {
+            self.hide(layer)
+        }
+    }
+
+    /// returns the new visibility of the layer
+    pub fn toggle(&mut self, layer: i32) -> bool {
+        let visible = !self.is_visible(layer);
+        self.set_visible(layer, visible);
+        visible
+    }
+
+    pub fn is_visible(&self, layer: i32) -> bool {
+        !self.hidden.contains(&layer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game_engine::layers::Layers;
+
+    #[test]
+    fn test_toggle_layers() {
+        let mut layers = Layers::new();
+        assert!(layers.is_visible(3));
+        assert!(!layers.toggle(3));
+        assert!(!layers.is_visible(3));
+        layers.hide(-1).show(3);
+        assert!(layers.is_visible(3));
+        assert!(!layers.is_visible(-1));
+        assert!(layers.toggle(-1));
+    }
+}
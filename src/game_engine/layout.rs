@@ -0,0 +1,120 @@
+//! # Layout
+//! Where the board ends up in the window, recomputed by `game_loop` when the window is resized.
+
+use crate::game_engine::game_data_model::GameDataModel;
+use crate::game_engine::input::board_cell;
+
+/// How the board follows the window size
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ScaleMode {
+    /// blocks keep their size, the board stays in the top left corner
+    Fixed,
+    /// blocks grow and shrink with the window, ignoring the aspect ratio
+    Stretch,
+    /// blocks keep the aspect ratio, the board is centered with bars around it
+    Letterbox,
+}
+
+impl ScaleMode {
+    /// parses the names used in `game.config`
+    pub fn from_name(name: &str) -> Option<ScaleMode> {
+        match name {
+            "fixed" => Some(ScaleMode::Fixed),
+            "stretch" => Some(ScaleMode::Stretch),
+            "letterbox" => Some(ScaleMode::Letterbox),
+            _ => None,
+        }
+    }
+}
+
+/// Block size and board position in window coordinates
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct BoardLayout {
+    pub window_size: [f64; 2],
+    /// top left corner of the board
+    pub offset: [f32; 2],
+    pub block_width: f32,
+    pub block_height: f32,
+    pub board_width: f32,
+    pub board_height: f32,
+}
+
+impl BoardLayout {
+    /// layout for the window size the model asks for
+    pub fn from_model<T: GameDataModel>(model: &T) -> Self {
+        BoardLayout {
+            window_size: [model.get_window_width() as f64, model.get_window_height() as f64],
+            offset: [0.0, 0.0],
+            block_width: model.get_block_width(),
+            block_height: model.get_block_height(),
+            board_width: model.get_board_width(),
+            board_height: model.get_board_height(),
+        }
+    }
+
+    /// layout after the window was resized to `window_size`
+    pub fn fit<T: GameDataModel>(model: &T, window_size: [f64; 2], mode: ScaleMode) -> Self {
+        let layout = BoardLayout::from_model(model);
+        let [width, height] = layout.window_size;
+        if width <= 0.0 || height <= 0.0 {
+            return BoardLayout { window_size, ..layout };
+        }
+        let scale_x = (window_size[0] / width) as f32;
+        let scale_y = (window_size[1] / height) as f32;
+        match mode {
+            ScaleMode::Fixed => BoardLayout { window_size, ..layout },
+            ScaleMode::Stretch => layout.scaled(window_size, scale_x, scale_y, [0.0, 0.0]),
+            ScaleMode::Letterbox => {
+                let scale = scale_x.min(scale_y);
+                let offset = [
+                    (window_size[0] as f32 - width as f32 * scale) / 2.0,
+                    (window_size[1] as f32 - height as f32 * scale) / 2.0,
+                ];
+                layout.scaled(window_size, scale, scale, offset)
+            }
+        }
+    }
+
+    fn scaled(&self, window_size: [f64; 2], scale_x: f32, scale_y: f32, offset: [f32; 2]) -> Self {
+        BoardLayout {
+            window_size,
+            offset,
+            block_width: self.block_width * scale_x,
+            block_height: self.block_height * scale_y,
+            board_width: self.board_width * scale_x,
+            board_height: self.board_height * scale_y,
+        }
+    }
+
+    /// board cell under the window position, `None` outside of the board
+    pub fn cell_at(&self, position: [f64; 2]) -> Option<[i32; 2]> {
+        let position = [position[0] - self.offset[0] as f64, position[1] - self.offset[1] as f64];
+        board_cell(position, self.block_width, self.block_height, self.board_width, self.board_height)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game_engine::game_config::GameConfig;
+    use crate::game_engine::test_support::TestGame;
+    use crate::game_engine::layout::{BoardLayout, ScaleMode};
+
+    #[test]
+    fn test_fit_layout() {
+        let config = GameConfig::parse("width=100\nheight=100\nmap_size=10\nboard_width=100\nboard_height=100").unwrap();
+        let game = TestGame::new(config);
+
+        let fixed = BoardLayout::fit(&game, [200.0, 100.0], ScaleMode::Fixed);
+        assert_eq!((fixed.block_width, fixed.block_height), (10.0, 10.0));
+
+        let stretched = BoardLayout::fit(&game, [200.0, 100.0], ScaleMode::Stretch);
+        assert_eq!((stretched.block_width, stretched.block_height), (20.0, 10.0));
+        assert_eq!(stretched.cell_at([190.0, 5.0]), Some([9, 0]));
+
+        let letterboxed = BoardLayout::fit(&game, [200.0, 100.0], ScaleMode::Letterbox);
+        assert_eq!((letterboxed.block_width, letterboxed.block_height), (10.0, 10.0));
+        assert_eq!(letterboxed.offset, [50.0, 0.0]);
+        assert_eq!(letterboxed.cell_at([45.0, 5.0]), None);
+        assert_eq!(letterboxed.cell_at([55.0, 5.0]), Some([0, 0]));
+    }
+}
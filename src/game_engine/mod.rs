@@ -2,132 +2,279 @@ pub mod point3d;
 pub mod game_data_model;
 pub mod shapes;
 pub mod commands;
+pub mod shape_file;
+pub mod game_config;
+pub mod grid;
+pub mod software_renderer;
+pub mod snapshot;
+pub mod layers;
+pub mod backend;
+pub mod piston_backend;
+pub mod headless_backend;
+pub mod input;
+pub mod bindings;
+pub mod timestep;
+pub mod layout;
+pub mod background;
+pub mod geometry;
+pub mod transform;
+pub mod scene;
+#[cfg(test)]
+pub(crate) mod test_support;
 
-use crate::{BLACK, GREEN, RED};
-use crate::game_engine::shapes::{ShapeKind, Block};
+use crate::game_engine::shapes::{Shape, Block, BlendMode, Point, Size};
+use crate::game_engine::transform::Affine;
 use crate::game_engine::game_data_model::GameDataModel;
-use crate::game_engine::game_board::{GameBoard, Pixel, PixelMap};
-use std::collections::HashMap;
-use rayon::iter::{IntoParallelRefIterator, ParallelBridge, ParallelIterator};
-use opengl_graphics::{GlGraphics, OpenGL};
-use piston_window::{PistonWindow as Window, WindowSettings};
-use piston::{RenderArgs, Events, EventSettings, RenderEvent, UpdateEvent, PressEvent, ReleaseEvent, ResizeEvent, Button, Key};
-use graphics::{clear, rectangle};
+use crate::game_engine::game_board::{PixelBuffer, PixelGrid, PixelStore};
 use graphics::types::Color;
 use crate::game_engine::game_events::PistonGameEvents;
+use crate::game_engine::layers::Layers;
+use crate::game_engine::backend::{Backend, BackendEvent, Frame, UpdateArgs};
+use crate::game_engine::input::{InputEvent, InputState, MouseTracker};
+use crate::game_engine::layout::BoardLayout;
+use crate::game_engine::timestep::{FixedTimestep, LoopSettings};
 
 
 pub mod game_board;
 pub mod game_events;
 
-pub struct GameEngineData {
-    gl: GlGraphics,
-    window: Window,
+pub use crate::game_engine::piston_backend::{EngineError, EngineSettings, GameEngineData, init_game_engine};
+
+/// `geometry` is the shape list the blocks' indices point into, many blocks can share one shape,
+/// the game loop draws into a `PixelGrid` with `draw_shapes_into` instead
+pub fn draw_shapes(shapes: &[Block], geometry: &[Shape]) -> PixelBuffer {
+    let mut pixels = PixelBuffer::new();
+    draw_shapes_into(shapes, geometry, &mut pixels);
+    pixels
 }
 
-pub fn init_game_engine(size: [f64; 2], opengl: OpenGL) -> GameEngineData {
-    let window = WindowSettings::new("Game", size)
-        .graphics_api(opengl)
-        .exit_on_esc(true)
-        .build()
-        .unwrap();
-    let gl = GlGraphics::new(opengl);
-    GameEngineData {
-        gl,
-        window,
-    }
+/// draws the shapes into any pixel store, e.g. a `PixelGrid`
+pub fn draw_shapes_into<P: PixelStore>(shapes: &[Block], geometry: &[Shape], pixels: &mut P) {
+    draw_layers_into(shapes, geometry, &Layers::new(), pixels);
 }
 
-pub fn draw_shapes(shapes: &Vec<Block>, point_list: &Vec<Vec<f32>>) -> PixelMap {
-    let mut pixels: PixelMap = HashMap::new();
-    shapes.iter().for_each(|block| {
-        let k = &point_list[block.index];
-        match block.shape {
-            ShapeKind::Rect => {
-                game_board::draw_rectangle(
-                    block.x + k[0], block.y + k[1], k[2], k[3], &mut pixels,
-                    Color::from(block.color),
-                );
+/// draws only the blocks on visible layers
+pub fn draw_layers(shapes: &[Block], geometry: &[Shape], layers: &Layers) -> PixelBuffer {
+    let mut pixels = PixelBuffer::new();
+    draw_layers_into(shapes, geometry, layers, &mut pixels);
+    pixels
+}
+
+/// blocks are drawn from the lowest layer up, blocks on the same layer in their order,
+/// blocks without a shape or with geometry of another kind are skipped
+pub fn draw_layers_into<P: PixelStore>(shapes: &[Block], geometry: &[Shape], layers: &Layers, pixels: &mut P) {
+    let placed = shapes.iter().map(|b| (*b, block_affine(b, &Affine::identity()))).collect();
+    draw_placed(placed, geometry, layers, pixels);
+}
+
+/// maps the block's geometry onto the board, `parent` places the block itself
+pub(crate) fn block_affine(block: &Block, parent: &Affine) -> Affine {
+    parent.compose(&Affine::translation(block.x, block.y)).compose(&block.transform.affine())
+}
+
+/// draws blocks already placed on the board, see `block_affine`
+pub(crate) fn draw_placed<P: PixelStore>(mut placed: Vec<(Block, Affine)>, geometry: &[Shape], layers: &Layers, pixels: &mut P) {
+    placed.retain(|(b, _)| layers.is_visible(b.layer));
+    // stable, keeps the order within a layer
+    placed.sort_by_key(|(b, _)| b.layer);
+    placed.iter().for_each(|(block, affine)| {
+        let shape = match block.get_shape(geometry) {
+            Some(shape) if shape.kind() == block.shape => shape,
+            _ => return,
+        };
+        if block.blend == BlendMode::Replace {
+            return draw_transformed(block, shape, affine, pixels);
+        }
+        // drawn on its own first, so overlapping parts of the block are only blended once
+        let mut layer = PixelBuffer::new();
+        draw_transformed(block, shape, affine, &mut layer);
+        layer.iter().for_each(|p| {
+            game_board::blend_pixel(pixels, p.point.x, p.point.y, p.color, block.blend)
+        });
+    });
+}
+
+/// draws the geometry mapped onto the board by `affine`, which takes the place of the block position,
+/// rotated rects and ellipses become polygons, mapped points and sizes are rounded to whole pixels
+fn draw_transformed<P: PixelStore>(block: &Block, shape: &Shape, affine: &Affine, pixels: &mut P) {
+    // geometry is mapped to board coordinates, so the block must not move it again
+    let placed = Block { x: 0.0, y: 0.0, ..*block };
+    let map = |p: &Point| round_point(affine.apply(*p));
+    let shape = match shape {
+        Shape::Rect { offset, size } => {
+            let corners = [(0.0, 0.0), (size.x, 0.0), (size.x, size.y), (0.0, size.y)];
+            let corners: Vec<Point> = corners.iter()
+                .map(|&(x, y)| map(&Point { x: offset.x + x, y: offset.y + y }))
+                .collect();
+            if affine.is_axis_aligned() {
+                let (a, b) = (corners[0], corners[2]);
+                Shape::Rect {
+                    offset: Point { x: a.x.min(b.x), y: a.y.min(b.y) },
+                    size: Size { x: (b.x - a.x).abs(), y: (b.y - a.y).abs() },
+                }
+            } else {
+                Shape::Polygon(corners)
             }
-            ShapeKind::Circle => {
-                game_board::draw_circle(
-                    block.x + k[0], block.y + k[1], k[2], &mut pixels,
-                    Color::from(block.color),
-                );
+        }
+        Shape::Circle { center, radius } => match affine.uniform_scale() {
+            Some(scale) => Shape::Circle { center: map(center), radius: (radius * scale).round() },
+            None => ellipse_polygon(*center, Size { x: *radius, y: *radius }, affine),
+        },
+        Shape::Ellipse { center, radii } if affine.is_axis_aligned() => Shape::Ellipse {
+            center: map(center),
+            radii: Size { x: (radii.x * affine.a.abs()).round(), y: (radii.y * affine.d.abs()).round() },
+        },
+        Shape::Ellipse { center, radii } => ellipse_polygon(*center, *radii, affine),
+        Shape::Line { from, to } => Shape::Line { from: map(from), to: map(to) },
+        Shape::Polygon(points) => Shape::Polygon(points.iter().map(map).collect()),
+        Shape::Polyline(points) => Shape::Polyline(points.iter().map(map).collect()),
+    };
+    draw_untransformed(&placed, &shape, pixels);
+}
+
+/// the rasterizers step in whole pixels, so they have to start on one
+fn round_point(p: Point) -> Point {
+    // adding 0.0 turns -0.0 into 0.0
+    Point { x: p.x.round() + 0.0, y: p.y.round() + 0.0 }
+}
+
+/// transformed ellipse outline as a polygon, with about one corner every 2 pixels
+fn ellipse_polygon(center: Point, radii: Size, affine: &Affine) -> Shape {
+    let stretch = (affine.a.hypot(affine.b)).max(affine.c.hypot(affine.d));
+    let circumference = 2.0 * std::f32::consts::PI * radii.x.max(radii.y) * stretch;
+    let corners = (circumference / 2.0).ceil().clamp(12.0, 360.0) as usize;
+    Shape::Polygon((0..corners).map(|i| {
+        let angle = i as f32 * 2.0 * std::f32::consts::PI / corners as f32;
+        round_point(affine.apply(Point { x: center.x + radii.x * angle.cos(), y: center.y + radii.y * angle.sin() }))
+    }).collect())
+}
+
+fn draw_untransformed<P: PixelStore>(block: &Block, shape: &Shape, pixels: &mut P) {
+    match shape {
+        Shape::Rect { offset, size } => {
+            let (x, y) = (block.x + offset.x, block.y + offset.y);
+            if let Some(color) = block.get_fill_color() {
+                game_board::draw_rectangle(x, y, size.x, size.y, pixels, Color::from(color));
             }
-            ShapeKind::Ellipse => {
-                game_board::draw_ellipse(
-                    block.x + k[0], block.y + k[1], k[2], k[3], &mut pixels,
-                    Color::from(block.color),
+            if let Some(color) = block.get_outline_color() {
+                game_board::draw_rectangle_outline(
+                    x, y, size.x, size.y, block.stroke, pixels, Color::from(color),
                 );
             }
-            ShapeKind::Polygon => {
-                game_board::draw_polygon(k, &block, &mut pixels)
+        }
+        Shape::Circle { center, radius } => {
+            let (x, y) = (block.x + center.x, block.y + center.y);
+            if let Some(color) = block.get_fill_color() {
+                game_board::fill_circle(x, y, *radius, pixels, Color::from(color));
+            }
+            if let Some(color) = block.get_outline_color() {
+                game_board::draw_thick_circle(x, y, *radius, block.stroke, pixels, Color::from(color));
+            }
+        }
+        Shape::Ellipse { center, radii } => {
+            let (x, y) = (block.x + center.x, block.y + center.y);
+            if let Some(color) = block.get_fill_color() {
+                game_board::fill_ellipse(x, y, radii.x, radii.y, pixels, Color::from(color));
             }
-            ShapeKind::Line => {
-                game_board::draw_line(
-                    block.x + k[0], block.y + k[1],
-                    block.x + k[2], block.y + k[3],
-                    &mut pixels,
-                    Color::from(block.color),
+            if let Some(color) = block.get_outline_color() {
+                game_board::draw_thick_ellipse(
+                    x, y, radii.x, radii.y, block.stroke, pixels, Color::from(color),
                 );
             }
-            ShapeKind::None => {
-                panic!("This should not happen")
+        }
+        Shape::Polygon(_) => {
+            let k = shape.to_points();
+            if let Some(color) = block.get_fill_color() {
+                game_board::fill_polygon(&k, block, pixels, Color::from(color));
             }
-        };
-    });
-    pixels
+            if let Some(color) = block.get_outline_color() {
+                game_board::draw_polygon_outline(&k, block, pixels, Color::from(color));
+            }
+        }
+        Shape::Polyline(_) => {
+            game_board::draw_polyline(&shape.to_points(), block, pixels)
+        }
+        Shape::Line { from, to } => {
+            game_board::draw_thick_line(
+                block.x + from.x, block.y + from.y,
+                block.x + to.x, block.y + to.y,
+                block.stroke, pixels,
+                Color::from(block.color),
+            );
+        }
+    }
 }
 
-pub fn game_loop<T: GameDataModel + PistonGameEvents>(mut app: T, mut game_data: GameEngineData) {
-    use graphics::*;
-    let mut events = Events::new(EventSettings::new());
-    // drawing context
-    while let Some(e) = events.next(&mut game_data.window) {
-        if let Some(args) = e.render_args() {
-            // get all the drawable
-            let pixels = app.update_game_board(&args);
-            let block_width = app.get_block_width();
-            let block_height = app.get_block_height();
-            let board_width = app.get_board_width();
-            let board_height = app.get_board_height();
-            let base_rect = rectangle::rectangle_by_corners(
-                0.0, 0.0,
-                block_width as f64, block_height as f64,
-            );
-            const GRID_COLOR: [f32; 4] = [0.3, 0.3, 0.3, 0.3];
-            // doing drawing stuffS
-            game_data.gl.draw(args.viewport(), |c, gl| {
-                // Clear the screen.
-                clear(BLACK, gl);
-                let s = (block_width * 2.0) as i32;
-                for ix in (s..board_width as i32).step_by(s as usize) {
-                    let l = [ix as f64, 0.0, ix as f64, board_height as f64];
-                    line(GRID_COLOR, 1.0, l, c.transform, gl);
+pub fn game_loop<T: GameDataModel + PistonGameEvents, B: Backend>(mut app: T, mut backend: B) {
+    run_game_loop(&mut app, &mut backend);
+}
+
+/// like `game_loop`, updating at the rate in `settings` instead of the backend's
+pub fn game_loop_with_settings<T: GameDataModel + PistonGameEvents, B: Backend>(mut app: T, mut backend: B, settings: &LoopSettings) {
+    run_game_loop_with_settings(&mut app, &mut backend, settings);
+}
+
+/// runs the loop until the backend closes, borrowing the game and backend
+/// so they can be inspected afterwards, e.g. in tests with a `HeadlessBackend`
+pub fn run_game_loop<T: GameDataModel + PistonGameEvents, B: Backend>(app: &mut T, backend: &mut B) {
+    let settings = backend.loop_settings();
+    run_game_loop_with_settings(app, backend, &settings);
+}
+
+/// `update` is called with a fixed `dt`, as often as needed to keep up with the time
+/// the backend reports, renders get the interpolation `alpha` between updates
+pub fn run_game_loop_with_settings<T: GameDataModel + PistonGameEvents, B: Backend>(app: &mut T, backend: &mut B, settings: &LoopSettings) {
+    let mut mouse = MouseTracker::new();
+    let mut input_state = InputState::new();
+    let mut timestep = FixedTimestep::new(settings);
+    // known once the backend reports it, until then the size the model asks for is used
+    let mut window_size: Option<[f64; 2]> = None;
+    let layout = |app: &T, window_size: Option<[f64; 2]>| match window_size {
+        Some(size) => BoardLayout::fit(app, size, app.get_scale_mode()),
+        None => BoardLayout::from_model(app),
+    };
+    // reused between frames, so drawing does not allocate
+    let mut pixels = PixelGrid::from_model(app);
+    while let Some(e) = backend.poll_event() {
+        match e {
+            BackendEvent::Render(mut args) => {
+                args.alpha = timestep.alpha(args.ext_dt);
+                if window_size != Some(args.window_size) {
+                    window_size = Some(args.window_size);
+                    app.on_resize(&layout(app, window_size));
                 }
-                let s = (block_height * 2.0) as i32;
-                for iy in (s..board_height as i32).step_by(s as usize)  {
-                    let l = [0.0, iy as f64, board_height as f64, iy as f64];
-                    line(GRID_COLOR, 1.0, l, c.transform, gl);
+                // get all the drawable
+                pixels.reset_to_model(app);
+                app.update_game_board(&args, &mut pixels);
+                backend.present(&Frame::with_layout(&pixels, app, &layout(app, window_size)), &args);
+            }
+            BackendEvent::Input(input) => {
+                match input {
+                    InputEvent::Press(button) => app.handle_press_events(&button),
+                    InputEvent::Release(button) => app.handle_release_events(&button),
+                    _ => {}
                 }
-                pixels.values().for_each(|pixel| {
-                    let transform = c.transform.trans(
-                        (pixel.point.x * block_width) as f64,
-                        (pixel.point.y * block_height) as f64
-                    );
-                    rectangle(pixel.color, base_rect, transform, gl);
-                });
-            });
-        }
-        if let Some(button) = e.press_args() {
-            app.handle_press_events(&button);
-        }
-        if let Some(button) = e.release_args() {
-            app.handle_release_events(&button);
-        }
-        if let Some(args) = e.update_args() {
-            app.update(&args);
+                let board = layout(app, window_size);
+                for event in mouse.handle(&input, |p| board.cell_at(p)) {
+                    app.handle_mouse_events(&event);
+                }
+                input_state.handle(&input);
+                input_state.set_cursor(mouse.position());
+            }
+            BackendEvent::Update(args) => {
+                let step = UpdateArgs { dt: timestep.step() };
+                for _ in 0..timestep.advance(args.dt) {
+                    app.update(&step, &input_state);
+                    // presses are only new in the first update
+                    input_state.end_frame();
+                }
+            }
+            BackendEvent::Resize { window_size: size, .. } => {
+                if window_size != Some(size) {
+                    window_size = Some(size);
+                    app.on_resize(&layout(app, window_size));
+                }
+            }
         }
     }
 }
@@ -0,0 +1,501 @@
+//! # Piston Backend
+//! Shows frames in a Piston window using OpenGL, and translates Piston events to engine events
+
+use std::fmt;
+use opengl_graphics::{GlGraphics, OpenGL, Texture, TextureSettings};
+use piston_window::{PistonWindow as Window, Window as _, WindowSettings};
+use piston::{
+    Event, EventLoop, Events, EventSettings, RenderEvent, UpdateEvent, PressEvent, ReleaseEvent, ResizeEvent,
+    MouseCursorEvent, MouseScrollEvent,
+};
+use crate::BLACK;
+use crate::game_engine::background::{Background, ImageCache, Trail};
+use crate::game_engine::backend::{Backend, BackendEvent, Frame, RenderArgs, UpdateArgs};
+use crate::game_engine::grid::grid_lines;
+use crate::game_engine::input::{Button, GamepadButton, GamepadHat, HatDirection, InputEvent, Key, MouseButton};
+use crate::game_engine::timestep::{LoopSettings, LoopSettingsError};
+
+pub struct GameEngineData {
+    gl: GlGraphics,
+    window: Window,
+    events: Events,
+    loop_settings: LoopSettings,
+    exit_key: ExitKey,
+    background: ImageCache<Texture>,
+    /// the window's buffers are swapped every frame, so trails are drawn again from here
+    trail: Trail,
+}
+
+/// Pressing the exit key closes the window, neither the press nor its release reach the game
+#[derive(Copy, Clone, Debug)]
+struct ExitKey {
+    key: Option<Key>,
+    held: bool,
+}
+
+impl ExitKey {
+    /// true if the event belongs to the exit key and is dropped
+    fn handle(&mut self, event: &BackendEvent) -> bool {
+        match event {
+            BackendEvent::Input(InputEvent::Press(Button::Keyboard(key))) if self.key == Some(*key) => {
+                self.held = true;
+                true
+            }
+            BackendEvent::Input(InputEvent::Release(Button::Keyboard(key))) if self.held && self.key == Some(*key) => {
+                self.held = false;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum EngineError {
+    /// the window or its OpenGL context could not be created
+    Window(String),
+    /// the loop would never update the game
+    LoopSettings(LoopSettingsError),
+}
+
+impl fmt::Display for EngineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EngineError::Window(reason) => write!(f, "failed to create window: {}", reason),
+            EngineError::LoopSettings(e) => write!(f, "invalid loop settings: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for EngineError {}
+
+impl From<LoopSettingsError> for EngineError {
+    fn from(e: LoopSettingsError) -> Self {
+        EngineError::LoopSettings(e)
+    }
+}
+
+/// Window and loop settings for the Piston backend
+///
+/// ```no_run
+/// # use pixel_game_engine::game_engine::piston_backend::EngineSettings;
+/// # use pixel_game_engine::game_engine::input::Key;
+/// let engine = EngineSettings::new([800.0, 800.0])
+///     .title("Snake")
+///     .vsync(true)
+///     .exit_key(Some(Key::Q))
+///     .build();
+/// ```
+#[derive(Clone, Debug)]
+pub struct EngineSettings {
+    title: String,
+    size: [f64; 2],
+    opengl: OpenGL,
+    vsync: bool,
+    max_fps: u64,
+    loop_settings: LoopSettings,
+    resizable: bool,
+    fullscreen: bool,
+    exit_key: Option<Key>,
+}
+
+impl EngineSettings {
+    pub fn new(size: [f64; 2]) -> Self {
+        EngineSettings {
+            title: "Game".to_string(),
+            size,
+            opengl: OpenGL::V3_2,
+            vsync: false,
+            max_fps: 60,
+            loop_settings: LoopSettings::default(),
+            resizable: true,
+            fullscreen: false,
+            exit_key: Some(Key::Escape),
+        }
+    }
+
+    pub fn title(&mut self, title: &str) -> &mut Self {
+        self.title = title.to_string();
+        self
+    }
+
+    pub fn opengl(&mut self, opengl: OpenGL) -> &mut Self {
+        self.opengl = opengl;
+        self
+    }
+
+    pub fn vsync(&mut self, vsync: bool) -> &mut Self {
+        self.vsync = vsync;
+        self
+    }
+
+    /// most frames rendered per second
+    pub fn max_fps(&mut self, fps: u64) -> &mut Self {
+        self.max_fps = fps;
+        self
+    }
+
+    /// updates per second, see `LoopSettings`
+    pub fn updates_per_second(&mut self, ups: f64) -> &mut Self {
+        self.loop_settings.updates_per_second = ups;
+        self
+    }
+
+    pub fn loop_settings(&mut self, settings: LoopSettings) -> &mut Self {
+        self.loop_settings = settings;
+        self
+    }
+
+    pub fn resizable(&mut self, resizable: bool) -> &mut Self {
+        self.resizable = resizable;
+        self
+    }
+
+    pub fn fullscreen(&mut self, fullscreen: bool) -> &mut Self {
+        self.fullscreen = fullscreen;
+        self
+    }
+
+    /// key closing the window, `None` to only close it from the window itself
+    pub fn exit_key(&mut self, key: Option<Key>) -> &mut Self {
+        self.exit_key = key;
+        self
+    }
+
+    pub fn build(&self) -> Result<GameEngineData, EngineError> {
+        // checked before the window opens
+        self.loop_settings.validate()?;
+        let window = WindowSettings::new(self.title.clone(), self.size)
+            .graphics_api(self.opengl)
+            .vsync(self.vsync)
+            .resizable(self.resizable)
+            .fullscreen(self.fullscreen)
+            // handled in `poll_event`, so any key can close the window
+            .exit_on_esc(false)
+            .build()
+            .map_err(|e: Box<dyn std::error::Error>| EngineError::Window(e.to_string()))?;
+        let gl = GlGraphics::new(self.opengl);
+        let mut events = Events::new(EventSettings::new());
+        events.set_max_fps(self.max_fps);
+        events.set_ups(self.loop_settings.updates_per_second.ceil() as u64);
+        events.set_ups_reset(self.loop_settings.max_catch_up_steps as u64);
+        Ok(GameEngineData {
+            gl,
+            window,
+            events,
+            loop_settings: self.loop_settings,
+            exit_key: ExitKey { key: self.exit_key, held: false },
+            background: ImageCache::new(),
+            trail: Trail::new(),
+        })
+    }
+}
+
+/// window titled "Game" that closes on escape, see `EngineSettings` for more options
+pub fn init_game_engine(size: [f64; 2], opengl: OpenGL) -> Result<GameEngineData, EngineError> {
+    EngineSettings::new(size).opengl(opengl).build()
+}
+
+impl From<piston::Key> for Key {
+    fn from(key: piston::Key) -> Self {
+        match key {
+            piston::Key::Backspace => Key::Backspace,
+            piston::Key::Tab => Key::Tab,
+            piston::Key::Return => Key::Return,
+            piston::Key::Escape => Key::Escape,
+            piston::Key::Space => Key::Space,
+            piston::Key::Quote => Key::Quote,
+            piston::Key::Comma => Key::Comma,
+            piston::Key::Minus => Key::Minus,
+            piston::Key::Period => Key::Period,
+            piston::Key::Slash => Key::Slash,
+            piston::Key::D0 => Key::D0,
+            piston::Key::D1 => Key::D1,
+            piston::Key::D2 => Key::D2,
+            piston::Key::D3 => Key::D3,
+            piston::Key::D4 => Key::D4,
+            piston::Key::D5 => Key::D5,
+            piston::Key::D6 => Key::D6,
+            piston::Key::D7 => Key::D7,
+            piston::Key::D8 => Key::D8,
+            piston::Key::D9 => Key::D9,
+            piston::Key::Semicolon => Key::Semicolon,
+            piston::Key::Equals => Key::Equals,
+            piston::Key::LeftBracket => Key::LeftBracket,
+            piston::Key::Backslash => Key::Backslash,
+            piston::Key::RightBracket => Key::RightBracket,
+            piston::Key::Backquote => Key::Backquote,
+            piston::Key::A => Key::A,
+            piston::Key::B => Key::B,
+            piston::Key::C => Key::C,
+            piston::Key::D => Key::D,
+            piston::Key::E => Key::E,
+            piston::Key::F => Key::F,
+            piston::Key::G => Key::G,
+            piston::Key::H => Key::H,
+            piston::Key::I => Key::I,
+            piston::Key::J => Key::J,
+            piston::Key::K => Key::K,
+            piston::Key::L => Key::L,
+            piston::Key::M => Key::M,
+            piston::Key::N => Key::N,
+            piston::Key::O => Key::O,
+            piston::Key::P => Key::P,
+            piston::Key::Q => Key::Q,
+            piston::Key::R => Key::R,
+            piston::Key::S => Key::S,
+            piston::Key::T => Key::T,
+            piston::Key::U => Key::U,
+            piston::Key::V => Key::V,
+            piston::Key::W => Key::W,
+            piston::Key::X => Key::X,
+            piston::Key::Y => Key::Y,
+            piston::Key::Z => Key::Z,
+            piston::Key::Delete => Key::Delete,
+            piston::Key::CapsLock => Key::CapsLock,
+            piston::Key::F1 => Key::F1,
+            piston::Key::F2 => Key::F2,
+            piston::Key::F3 => Key::F3,
+            piston::Key::F4 => Key::F4,
+            piston::Key::F5 => Key::F5,
+            piston::Key::F6 => Key::F6,
+            piston::Key::F7 => Key::F7,
+            piston::Key::F8 => Key::F8,
+            piston::Key::F9 => Key::F9,
+            piston::Key::F10 => Key::F10,
+            piston::Key::F11 => Key::F11,
+            piston::Key::F12 => Key::F12,
+            piston::Key::PrintScreen => Key::PrintScreen,
+            piston::Key::ScrollLock => Key::ScrollLock,
+            piston::Key::Pause => Key::Pause,
+            piston::Key::Insert => Key::Insert,
+            piston::Key::Home => Key::Home,
+            piston::Key::PageUp => Key::PageUp,
+            piston::Key::End => Key::End,
+            piston::Key::PageDown => Key::PageDown,
+            piston::Key::Right => Key::Right,
+            piston::Key::Left => Key::Left,
+            piston::Key::Down => Key::Down,
+            piston::Key::Up => Key::Up,
+            piston::Key::NumPad0 => Key::NumPad0,
+            piston::Key::NumPad1 => Key::NumPad1,
+            piston::Key::NumPad2 => Key::NumPad2,
+            piston::Key::NumPad3 => Key::NumPad3,
+            piston::Key::NumPad4 => Key::NumPad4,
+            piston::Key::NumPad5 => Key::NumPad5,
+            piston::Key::NumPad6 => Key::NumPad6,
+            piston::Key::NumPad7 => Key::NumPad7,
+            piston::Key::NumPad8 => Key::NumPad8,
+            piston::Key::NumPad9 => Key::NumPad9,
+            piston::Key::NumPadDivide => Key::NumPadDivide,
+            piston::Key::NumPadMultiply => Key::NumPadMultiply,
+            piston::Key::NumPadMinus => Key::NumPadMinus,
+            piston::Key::NumPadPlus => Key::NumPadPlus,
+            piston::Key::NumPadEnter => Key::NumPadEnter,
+            piston::Key::NumPadPeriod => Key::NumPadPeriod,
+            piston::Key::LCtrl => Key::LCtrl,
+            piston::Key::LShift => Key::LShift,
+            piston::Key::LAlt => Key::LAlt,
+            piston::Key::LGui => Key::LGui,
+            piston::Key::RCtrl => Key::RCtrl,
+            piston::Key::RShift => Key::RShift,
+            piston::Key::RAlt => Key::RAlt,
+            piston::Key::RGui => Key::RGui,
+            other => Key::Other(other.code()),
+        }
+    }
+}
+
+impl From<piston::MouseButton> for MouseButton {
+    fn from(button: piston::MouseButton) -> Self {
+        match button {
+            piston::MouseButton::Left => MouseButton::Left,
+            piston::MouseButton::Right => MouseButton::Right,
+            piston::MouseButton::Middle => MouseButton::Middle,
+            piston::MouseButton::X1 => MouseButton::X1,
+            piston::MouseButton::X2 => MouseButton::X2,
+            piston::MouseButton::Button6 => MouseButton::Button6,
+            piston::MouseButton::Button7 => MouseButton::Button7,
+            piston::MouseButton::Button8 => MouseButton::Button8,
+            piston::MouseButton::Unknown => MouseButton::Unknown,
+        }
+    }
+}
+
+impl From<piston::HatState> for HatDirection {
+    fn from(state: piston::HatState) -> Self {
+        match state {
+            piston::HatState::Centered => HatDirection::Centered,
+            piston::HatState::Up => HatDirection::Up,
+            piston::HatState::Right => HatDirection::Right,
+            piston::HatState::Down => HatDirection::Down,
+            piston::HatState::Left => HatDirection::Left,
+            piston::HatState::RightUp => HatDirection::RightUp,
+            piston::HatState::RightDown => HatDirection::RightDown,
+            piston::HatState::LeftUp => HatDirection::LeftUp,
+            piston::HatState::LeftDown => HatDirection::LeftDown,
+        }
+    }
+}
+
+impl From<piston::Button> for Button {
+    fn from(button: piston::Button) -> Self {
+        match button {
+            piston::Button::Keyboard(key) => Button::Keyboard(key.into()),
+            piston::Button::Mouse(button) => Button::Mouse(button.into()),
+            piston::Button::Controller(button) => Button::Gamepad(GamepadButton {
+                gamepad: button.id,
+                button: button.button,
+            }),
+            piston::Button::Hat(hat) => Button::Hat(GamepadHat {
+                gamepad: hat.id,
+                hat: hat.which,
+                direction: hat.state.into(),
+            }),
+        }
+    }
+}
+
+fn translate_event(e: &Event) -> Option<BackendEvent> {
+    if let Some(args) = e.render_args() {
+        return Some(BackendEvent::Render(RenderArgs {
+            ext_dt: args.ext_dt,
+            alpha: 0.0,
+            window_size: args.window_size,
+            draw_size: args.draw_size,
+        }));
+    }
+    if let Some(args) = e.resize_args() {
+        return Some(BackendEvent::Resize { window_size: args.window_size, draw_size: args.draw_size });
+    }
+    if let Some(button) = e.press_args() {
+        return Some(BackendEvent::Input(InputEvent::Press(button.into())));
+    }
+    if let Some(button) = e.release_args() {
+        return Some(BackendEvent::Input(InputEvent::Release(button.into())));
+    }
+    if let Some(position) = e.mouse_cursor_args() {
+        return Some(BackendEvent::Input(InputEvent::MouseMove(position)));
+    }
+    if let Some(amount) = e.mouse_scroll_args() {
+        return Some(BackendEvent::Input(InputEvent::MouseScroll(amount)));
+    }
+    e.update_args().map(|args| BackendEvent::Update(UpdateArgs { dt: args.dt }))
+}
+
+impl Backend for GameEngineData {
+    fn poll_event(&mut self) -> Option<BackendEvent> {
+        // skip events the engine does not handle
+        while let Some(e) = self.events.next(&mut self.window) {
+            if let Some(event) = translate_event(&e) {
+                if self.exit_key.handle(&event) {
+                    if self.exit_key.held {
+                        self.window.set_should_close(true);
+                    }
+                    continue;
+                }
+                return Some(event);
+            }
+        }
+        None
+    }
+
+    fn loop_settings(&self) -> LoopSettings {
+        self.loop_settings
+    }
+
+    fn present(&mut self, frame: &Frame, args: &RenderArgs) {
+        use graphics::*;
+        let block_width = frame.block_width;
+        let block_height = frame.block_height;
+        let base_rect = rectangle::rectangle_by_corners(
+            0.0, 0.0,
+            block_width as f64, block_height as f64,
+        );
+        let lines = grid_lines(frame.board_width, frame.board_height, block_width, block_height, &frame.grid);
+        let viewport = Viewport {
+            rect: [0, 0, args.draw_size[0] as i32, args.draw_size[1] as i32],
+            window_size: args.window_size,
+            draw_size: args.draw_size,
+        };
+        let pixels = self.trail.pixels_for(frame);
+        // doing drawing stuffS
+        let offset = frame.offset;
+        let background = match &frame.background {
+            Background::Image(path) => self.background.get_or_load(path, |p| {
+                Texture::from_path(p, &TextureSettings::new()).ok()
+            }),
+            _ => None,
+        };
+        let window_size = args.window_size;
+        self.gl.draw(viewport, |c, gl| {
+            match (&frame.background, background) {
+                (Background::Color(color), _) => clear(*color, gl),
+                (Background::Image(_), Some(texture)) => {
+                    clear(BLACK, gl);
+                    let (width, height) = texture.get_size();
+                    let scale = c.transform.scale(window_size[0] / width as f64, window_size[1] / height as f64);
+                    image(texture, scale, gl);
+                }
+                // the trail holds the pixels of the earlier frames
+                (Background::Image(_), None) | (Background::None, _) => clear(BLACK, gl),
+            }
+            let c = c.trans(offset[0] as f64, offset[1] as f64);
+            for l in lines {
+                line(l.color, 1.0, l.points, c.transform, gl);
+            }
+            pixels.pixels().for_each(|pixel| {
+                let transform = c.transform.trans(
+                    (pixel.point.x * block_width) as f64,
+                    (pixel.point.y * block_height) as f64
+                );
+                rectangle(pixel.color, base_rect, transform, gl);
+            });
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game_engine::backend::BackendEvent;
+    use crate::game_engine::input::{Button, GamepadButton, GamepadHat, HatDirection, InputEvent, Key, MouseButton};
+    use crate::game_engine::piston_backend::{EngineError, EngineSettings, ExitKey};
+    use crate::game_engine::timestep::LoopSettingsError;
+
+    #[test]
+    fn test_translate_buttons() {
+        assert_eq!(Button::from(piston::Button::Keyboard(piston::Key::Left)), Button::Keyboard(Key::Left));
+        assert_eq!(Key::from(piston::Key::VolumeUp), Key::Other(piston::Key::VolumeUp.code()));
+        assert_eq!(
+            Button::from(piston::Button::Mouse(piston::MouseButton::Middle)),
+            Button::Mouse(MouseButton::Middle)
+        );
+        assert_eq!(
+            Button::from(piston::Button::Controller(piston::ControllerButton::new(1, 3))),
+            Button::Gamepad(GamepadButton { gamepad: 1, button: 3 })
+        );
+        assert_eq!(
+            Button::from(piston::Button::Hat(piston::ControllerHat::new(0, 1, piston::HatState::LeftUp))),
+            Button::Hat(GamepadHat { gamepad: 0, hat: 1, direction: HatDirection::LeftUp })
+        );
+    }
+
+    #[test]
+    fn test_invalid_updates_per_second() {
+        // rejected before a window is opened
+        let result = EngineSettings::new([100.0, 100.0]).updates_per_second(0.0).build();
+        assert!(matches!(result.err(), Some(EngineError::LoopSettings(LoopSettingsError::InvalidRate(_)))));
+    }
+
+    #[test]
+    fn test_exit_key_drops_press_and_release() {
+        let mut exit = ExitKey { key: Some(Key::Escape), held: false };
+        let event = |e| BackendEvent::Input(e);
+        // a release without a press, e.g. the key was held when the window opened
+        assert!(!exit.handle(&event(InputEvent::Release(Button::Keyboard(Key::Escape)))));
+        assert!(exit.handle(&event(InputEvent::Press(Button::Keyboard(Key::Escape)))));
+        assert!(!exit.handle(&event(InputEvent::Press(Button::Keyboard(Key::Space)))));
+        assert!(exit.handle(&event(InputEvent::Release(Button::Keyboard(Key::Escape)))));
+        assert!(!exit.handle(&event(InputEvent::Release(Button::Keyboard(Key::Escape)))));
+    }
+}
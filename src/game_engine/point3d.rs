@@ -1,5 +1,5 @@
 use std::ops;
-use std::ops::{Neg, Mul, Index};
+use std::ops::Mul;
 
 
 #[derive(Copy, Clone)]
@@ -142,6 +142,24 @@ impl ops::DivAssign<f32> for Point3D {
     }
 }
 
+pub fn dot(a: &Point3D, b: &Point3D) -> f32 {
+    a.x() * b.x() + a.y() * b.y() + a.z() * b.z()
+}
+
+pub fn unit_vector(a: &Point3D) -> Point3D {
+    *a / a.length()
+}
+
+pub fn cross(a: &Point3D, v: &Point3D) -> Point3D {
+    Point3D {
+        e: [
+            a.y() * v.z() - a.z() * v.y(),
+            a.z() * v.x() - a.x() * v.z(),
+            a.x() * v.y() - a.y() * v.x(),
+        ]
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::game_engine::point3d::Point3D;
@@ -197,22 +215,3 @@ mod tests {
         assert_eq!(v.e, [7f32, 3f32, 10f32])
     }
 }
-
-pub fn dot(a: &Point3D, b: &Point3D) -> f32 {
-    a.x() * b.x() + a.y() * b.y() + a.z() * b.z()
-}
-
-pub fn unit_vector(a: &Point3D) -> Point3D {
-    *a / a.length()
-}
-
-pub fn cross(a: &Point3D, v: &Point3D) -> Point3D {
-    Point3D {
-        e: [
-            a.y() * v.z() - a.z() * v.y(),
-            a.z() * v.x() - a.x() * v.z(),
-            a.x() * v.y() - a.y() * v.x(),
-        ]
-    }
-}
-
@@ -0,0 +1,265 @@
+//! # Scene
+//! Blocks grouped in a tree of nodes, e.g. a tank with a turret node on top of it.
+//! Nodes pass their position, transform, visibility and tint on to their children,
+//! so moving the tank moves the turret along.
+
+use std::collections::HashMap;
+use graphics::types::Color;
+use crate::game_engine::{block_affine, draw_placed};
+use crate::game_engine::game_board::{PixelBuffer, PixelStore};
+use crate::game_engine::layers::Layers;
+use crate::game_engine::shapes::{Block, Shape};
+use crate::game_engine::transform::{Affine, Transform};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct NodeId(usize);
+
+#[derive(Clone, Debug)]
+pub struct SceneNode {
+    /// position relative to the parent node
+    pub x: f32,
+    pub y: f32,
+    /// rotation and scale around the pivot, applied to the blocks and child nodes
+    pub transform: Transform,
+    /// hidden nodes hide their children as well
+    pub visible: bool,
+    /// multiplied with the block colors, and with the tint of the parents
+    pub tint: Color,
+    pub blocks: Vec<Block>,
+    parent: Option<NodeId>,
+    children: Vec<NodeId>,
+}
+
+impl SceneNode {
+    fn new(parent: Option<NodeId>) -> Self {
+        SceneNode {
+            x: 0.0,
+            y: 0.0,
+            transform: Transform::default(),
+            visible: true,
+            tint: [1.0, 1.0, 1.0, 1.0],
+            blocks: Vec::new(),
+            parent,
+            children: Vec::new(),
+        }
+    }
+
+    pub fn parent(&self) -> Option<NodeId> {
+        self.parent
+    }
+
+    pub fn children(&self) -> &[NodeId] {
+        &self.children
+    }
+
+    /// position and transform of the node, relative to its parent
+    pub fn local_affine(&self) -> Affine {
+        Affine::translation(self.x, self.y).compose(&self.transform.affine())
+    }
+}
+
+/// Tree of nodes, starting at a root node that can not be removed
+#[derive(Clone, Debug)]
+pub struct SceneGraph {
+    nodes: HashMap<NodeId, SceneNode>,
+    next_id: usize,
+}
+
+impl Default for SceneGraph {
+    fn default() -> Self {
+        let mut nodes = HashMap::new();
+        nodes.insert(NodeId(0), SceneNode::new(None));
+        SceneGraph { nodes, next_id: 1 }
+    }
+}
+
+impl SceneGraph {
+    pub fn new() -> Self {
+        SceneGraph::default()
+    }
+
+    pub fn root(&self) -> NodeId {
+        NodeId(0)
+    }
+
+    /// adds an empty node as last child of `parent`, `None` if the parent does not exist
+    pub fn add_node(&mut self, parent: NodeId) -> Option<NodeId> {
+        let id = NodeId(self.next_id);
+        self.nodes.get_mut(&parent)?.children.push(id);
+        self.nodes.insert(id, SceneNode::new(Some(parent)));
+        self.next_id += 1;
+        Some(id)
+    }
+
+    /// returns false if the node does not exist
+    pub fn add_block(&mut self, node: NodeId, block: Block) -> bool {
+        match self.nodes.get_mut(&node) {
+            Some(node) => {
+                node.blocks.push(block);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn node(&self, id: NodeId) -> Option<&SceneNode> {
+        self.nodes.get(&id)
+    }
+
+    pub fn node_mut(&mut self, id: NodeId) -> Option<&mut SceneNode> {
+        self.nodes.get_mut(&id)
+    }
+
+    /// removes the node along with all its children, the root stays
+    pub fn remove(&mut self, id: NodeId) -> bool {
+        if id == self.root() {
+            return false;
+        }
+        let node = match self.nodes.remove(&id) {
+            Some(node) => node,
+            None => return false,
+        };
+        if let Some(parent) = node.parent.and_then(|p| self.nodes.get_mut(&p)) {
+            parent.children.retain(|c| *c != id);
+        }
+        let mut pending = node.children;
+        while let Some(child) = pending.pop() {
+            if let Some(child) = self.nodes.remove(&child) {
+                pending.extend(child.children);
+            }
+        }
+        true
+    }
+
+    /// moves the node under another parent, fails if that would create a cycle
+    pub fn reparent(&mut self, id: NodeId, parent: NodeId) -> bool {
+        if id == self.root() || !self.nodes.contains_key(&id) || !self.nodes.contains_key(&parent) {
+            return false;
+        }
+        // the new parent can not be the node itself or below it
+        let mut ancestor = Some(parent);
+        while let Some(a) = ancestor {
+            if a == id {
+                return false;
+            }
+            ancestor = self.nodes[&a].parent;
+        }
+        if let Some(old) = self.nodes[&id].parent {
+            self.nodes.get_mut(&old).unwrap().children.retain(|c| *c != id);
+        }
+        self.nodes.get_mut(&parent).unwrap().children.push(id);
+        self.nodes.get_mut(&id).unwrap().parent = Some(parent);
+        true
+    }
+
+    /// maps the node's coordinates onto the board, combining the transforms of all parents
+    pub fn world_affine(&self, id: NodeId) -> Option<Affine> {
+        let mut affine = self.nodes.get(&id)?.local_affine();
+        let mut parent = self.nodes[&id].parent;
+        while let Some(p) = parent {
+            let node = &self.nodes[&p];
+            affine = node.local_affine().compose(&affine);
+            parent = node.parent;
+        }
+        Some(affine)
+    }
+
+    /// every block of the visible nodes, tinted and with its place on the board,
+    /// parents before children, children in the order they were added
+    pub fn flatten(&self) -> Vec<(Block, Affine)> {
+        let mut placed = Vec::new();
+        let root = self.root();
+        let mut pending = vec![(root, Affine::identity(), [1.0, 1.0, 1.0, 1.0])];
+        while let Some((id, parent_affine, parent_tint)) = pending.pop() {
+            let node = &self.nodes[&id];
+            if !node.visible {
+                continue;
+            }
+            let affine = parent_affine.compose(&node.local_affine());
+            let tint = multiply(parent_tint, node.tint);
+            for block in &node.blocks {
+                let tinted = Block {
+                    color: multiply(block.color, tint),
+                    outline_color: multiply(block.outline_color, tint),
+                    ..*block
+                };
+                placed.push((tinted, block_affine(block, &affine)));
+            }
+            // reversed, so the first child is drawn first
+            pending.extend(node.children.iter().rev().map(|c| (*c, affine, tint)));
+        }
+        placed
+    }
+}
+
+fn multiply(a: Color, b: Color) -> Color {
+    [a[0] * b[0], a[1] * b[1], a[2] * b[2], a[3] * b[3]]
+}
+
+/// draws the visible nodes of the scene, `geometry` is the shape list the blocks point into
+pub fn draw_scene(scene: &SceneGraph, geometry: &[Shape]) -> PixelBuffer {
+    let mut pixels = PixelBuffer::new();
+    draw_scene_into(scene, geometry, &Layers::new(), &mut pixels);
+    pixels
+}
+
+/// like `draw_layers_into`, blocks on higher layers are drawn over lower ones, whatever node they are in
+pub fn draw_scene_into<P: PixelStore>(scene: &SceneGraph, geometry: &[Shape], layers: &Layers, pixels: &mut P) {
+    draw_placed(scene.flatten(), geometry, layers, pixels);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::RED;
+    use crate::game_engine::game_board::PixelStore;
+    use crate::game_engine::scene::{draw_scene, SceneGraph};
+    use crate::game_engine::shapes::BlockBuilder;
+
+    #[test]
+    fn test_scene_graph() {
+        let mut points = Vec::new();
+        let mut scene = SceneGraph::new();
+        let tank = scene.add_node(scene.root()).unwrap();
+        let turret = scene.add_node(tank).unwrap();
+        scene.add_block(tank, BlockBuilder::rect(0.0, 0.0).points(vec![0.0, 0.0, 4.0, 2.0], &mut points).build());
+        scene.add_block(turret, BlockBuilder::rect(0.0, 0.0).points(vec![0.0, 0.0, 1.0, 1.0], &mut points)
+            .color(vec![1.0, 0.0, 0.0, 1.0]).build());
+        scene.node_mut(turret).unwrap().x = 1.0;
+
+        // moving the tank moves the turret along
+        scene.node_mut(tank).unwrap().x = 10.0;
+        let pixels = draw_scene(&scene, &points);
+        assert_eq!(pixels.get_color(11.0, 0.0), Some(RED));
+        assert_eq!(pixels.get_color(13.0, 1.0), Some([1.0, 1.0, 1.0, 1.0]));
+        assert_eq!(pixels.get_color(1.0, 0.0), None);
+
+        // tint and visibility are inherited
+        scene.node_mut(tank).unwrap().tint = [0.5, 0.5, 0.5, 1.0];
+        let pixels = draw_scene(&scene, &points);
+        assert_eq!(pixels.get_color(11.0, 0.0), Some([0.5, 0.0, 0.0, 1.0]));
+        scene.node_mut(tank).unwrap().visible = false;
+        assert!(draw_scene(&scene, &points).is_empty());
+
+        // turret can not become the parent of the tank
+        assert!(!scene.reparent(tank, turret));
+        assert!(scene.reparent(turret, scene.root()));
+        assert_eq!(draw_scene(&scene, &points).get_color(1.0, 0.0), Some(RED));
+        assert!(scene.remove(tank));
+        assert!(scene.node(turret).is_some());
+        assert!(!scene.remove(scene.root()));
+    }
+
+    #[test]
+    fn test_inherited_transform() {
+        let mut scene = SceneGraph::new();
+        let parent = scene.add_node(scene.root()).unwrap();
+        let child = scene.add_node(parent).unwrap();
+        scene.node_mut(parent).unwrap().x = 10.0;
+        scene.node_mut(parent).unwrap().transform.scale.x = 2.0;
+        scene.node_mut(child).unwrap().x = 3.0;
+        let affine = scene.world_affine(child).unwrap();
+        assert_eq!(affine.as_translation(), None);
+        let origin = affine.apply(crate::game_engine::shapes::Point { x: 0.0, y: 0.0 });
+        assert_eq!((origin.x, origin.y), (16.0, 0.0));
+    }
+}
@@ -0,0 +1,280 @@
+//! # Shape File
+//! Loads shapes from the plain text shape description format, see `game_cfg`
+//!
+//! ```text
+//! - Polygon
+//!   P, 23.0, 15.0, 25.0, 20.0, 20.0, 20.0
+//!   C, 1.0, 1.0, 0.0, 1.0
+//! #
+//! ```
+//!
+//! Every shape starts with `- <ShapeKind>`, followed by a `P` line with the points,
+//! an optional `C` line with the rgba color and ends with `#`.
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use crate::game_engine::shapes::{validate_points, Block, BlockBuilder, Shape, BlockError, ShapeKind, PointArity};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SyntaxErrorKind {
+    UnknownShape(String),
+    WrongPointCount { shape: ShapeKind, expected: PointArity, found: usize },
+    MalformedColor(String),
+    InvalidNumber(String),
+    MissingPoints(ShapeKind),
+    UnexpectedLine(String),
+    UnterminatedShape,
+    /// points or color the block does not accept, e.g. a negative radius
+    InvalidBlock(BlockError),
+}
+
+impl From<BlockError> for SyntaxErrorKind {
+    fn from(e: BlockError) -> Self {
+        match e {
+            BlockError::WrongPointCount { shape, expected, found } =>
+                SyntaxErrorKind::WrongPointCount { shape, expected, found },
+            e => SyntaxErrorKind::InvalidBlock(e),
+        }
+    }
+}
+
+/// Error in a shape file, line and column are 1 based
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyntaxError {
+    pub line: usize,
+    pub column: usize,
+    pub kind: SyntaxErrorKind,
+}
+
+#[derive(Debug)]
+pub enum ShapeFileError {
+    Io(io::Error),
+    Syntax(SyntaxError),
+}
+
+impl fmt::Display for SyntaxErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SyntaxErrorKind::UnknownShape(name) => write!(f, "unknown shape `{}`", name),
+            SyntaxErrorKind::WrongPointCount { shape, expected, found } =>
+                write!(f, "{:?} expects {}, found {}", shape, expected, found),
+            SyntaxErrorKind::MalformedColor(reason) => write!(f, "malformed color: {}", reason),
+            SyntaxErrorKind::InvalidNumber(value) => write!(f, "`{}` is not a number", value),
+            SyntaxErrorKind::MissingPoints(shape) => write!(f, "{:?} has no points", shape),
+            SyntaxErrorKind::UnexpectedLine(line) => write!(f, "unexpected line `{}`", line),
+            SyntaxErrorKind::UnterminatedShape => write!(f, "shape is not terminated with `#`"),
+            SyntaxErrorKind::InvalidBlock(e) => write!(f, "invalid block: {}", e),
+        }
+    }
+}
+
+impl fmt::Display for SyntaxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.kind)
+    }
+}
+
+impl fmt::Display for ShapeFileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ShapeFileError::Io(e) => write!(f, "failed to read shape file: {}", e),
+            ShapeFileError::Syntax(e) => write!(f, "invalid shape file at {}", e),
+        }
+    }
+}
+
+impl std::error::Error for SyntaxError {}
+
+impl std::error::Error for ShapeFileError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ShapeFileError::Io(e) => Some(e),
+            ShapeFileError::Syntax(e) => Some(e),
+        }
+    }
+}
+
+impl From<io::Error> for ShapeFileError {
+    fn from(e: io::Error) -> Self {
+        ShapeFileError::Io(e)
+    }
+}
+
+impl From<SyntaxError> for ShapeFileError {
+    fn from(e: SyntaxError) -> Self {
+        ShapeFileError::Syntax(e)
+    }
+}
+
+/// shape being read, until we hit the terminator
+struct PendingShape {
+    kind: ShapeKind,
+    line: usize,
+    points: Option<Vec<f32>>,
+    color: Option<Vec<f32>>,
+}
+
+/// reads the shape file and returns the blocks along with the points list they refer to
+pub fn load_shapes<P: AsRef<Path>>(path: P) -> Result<(Vec<Block>, Vec<Shape>), ShapeFileError> {
+    let source = fs::read_to_string(path)?;
+    Ok(parse_shapes(&source)?)
+}
+
+/// parses shape descriptions, returns the blocks along with the points list they refer to
+pub fn parse_shapes(source: &str) -> Result<(Vec<Block>, Vec<Shape>), SyntaxError> {
+    let mut blocks = Vec::new();
+    let mut points_list = Vec::new();
+    let mut pending: Option<PendingShape> = None;
+    for (line_index, raw_line) in source.lines().enumerate() {
+        let line = line_index + 1;
+        let trimmed = raw_line.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+        // column of the first non blank character
+        let indent = raw_line.chars().take_while(|c| c.is_whitespace()).count();
+        let error = |column: usize, kind: SyntaxErrorKind| SyntaxError { line, column, kind };
+        if let Some(name) = trimmed.strip_prefix('-') {
+            if let Some(shape) = pending {
+                return Err(SyntaxError { line: shape.line, column: 1, kind: SyntaxErrorKind::UnterminatedShape });
+            }
+            let name = name.trim();
+            let kind = ShapeKind::from_name(name).ok_or_else(|| {
+                // columns count chars, not bytes
+                let column = indent + 1 + trimmed[1..].chars().take_while(|c| c.is_whitespace()).count() + 1;
+                error(column, SyntaxErrorKind::UnknownShape(name.to_string()))
+            })?;
+            pending = Some(PendingShape { kind, line, points: None, color: None });
+            continue;
+        }
+        if trimmed == "#" {
+            let shape = pending.take()
+                .ok_or_else(|| error(indent + 1, SyntaxErrorKind::UnexpectedLine(trimmed.to_string())))?;
+            let shape_line = shape.line;
+            let points = shape.points
+                .ok_or(SyntaxError { line: shape.line, column: 1, kind: SyntaxErrorKind::MissingPoints(shape.kind) })?;
+            let mut builder = BlockBuilder::new(shape.kind, 0.0, 0.0);
+            builder.points(points, &mut points_list);
+            if let Some(color) = shape.color {
+                builder.color(color);
+            }
+            let block = builder.try_build()
+                .map_err(|e| SyntaxError { line: shape_line, column: 1, kind: e.into() })?;
+            blocks.push(block);
+            continue;
+        }
+        let fields = split_fields(raw_line);
+        let (tag_column, tag) = fields[0];
+        let shape = match pending.as_mut() {
+            Some(shape) if tag == "P" || tag == "C" => shape,
+            _ => return Err(error(tag_column, SyntaxErrorKind::UnexpectedLine(trimmed.to_string()))),
+        };
+        let mut values = Vec::new();
+        for &(column, value) in &fields[1..] {
+            match value.parse::<f32>() {
+                Ok(v) => values.push(v),
+                Err(_) if tag == "C" => return Err(error(
+                    column, SyntaxErrorKind::MalformedColor(format!("`{}` is not a number", value)),
+                )),
+                Err(_) => return Err(error(column, SyntaxErrorKind::InvalidNumber(value.to_string()))),
+            }
+        }
+        if tag == "P" {
+            validate_points(shape.kind, &values).map_err(|e| error(tag_column, e.into()))?;
+            shape.points = Some(values);
+        } else {
+            if values.len() != 4 {
+                return Err(error(tag_column, SyntaxErrorKind::MalformedColor(
+                    format!("expected 4 values (r, g, b, a), found {}", values.len())
+                )));
+            }
+            if let Some(i) = values.iter().position(|v| !(0.0..=1.0).contains(v)) {
+                let (column, value) = fields[i + 1];
+                return Err(error(column, SyntaxErrorKind::MalformedColor(
+                    format!("`{}` is outside 0.0..=1.0", value)
+                )));
+            }
+            shape.color = Some(values);
+        }
+    }
+    if let Some(shape) = pending {
+        return Err(SyntaxError { line: shape.line, column: 1, kind: SyntaxErrorKind::UnterminatedShape });
+    }
+    Ok((blocks, points_list))
+}
+
+/// splits a line on commas, returning trimmed fields along with their 1 based column
+fn split_fields(line: &str) -> Vec<(usize, &str)> {
+    let mut fields = Vec::new();
+    let mut start = 0;
+    for part in line.split(',') {
+        let leading = part.len() - part.trim_start().len();
+        let column = line[..start + leading].chars().count() + 1;
+        fields.push((column, part.trim()));
+        start += part.len() + 1;
+    }
+    fields
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game_engine::shape_file::{parse_shapes, SyntaxErrorKind};
+    use crate::game_engine::shapes::{BlockError, ShapeKind, PointArity};
+
+    #[test]
+    fn test_parse_game_cfg() {
+        let (blocks, points) = parse_shapes(include_str!("../../game_cfg")).unwrap();
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[0].shape, ShapeKind::Polygon);
+        assert_eq!(blocks[0].color, [1.0, 1.0, 0.0, 1.0]);
+        assert_eq!(blocks[0].get_shape_info(&points), vec![23.0, 15.0, 25.0, 20.0, 20.0, 20.0]);
+        assert_eq!(blocks[1].shape, ShapeKind::Circle);
+        assert_eq!(blocks[1].get_shape_info(&points), vec![50.0, 70.0, 15.0]);
+    }
+
+    #[test]
+    fn test_unknown_shape() {
+        let e = parse_shapes("- Hexagon\n  P, 1.0, 2.0\n#\n").unwrap_err();
+        assert_eq!((e.line, e.column), (1, 3));
+        assert_eq!(e.kind, SyntaxErrorKind::UnknownShape("Hexagon".to_string()));
+        // the ideographic space is one column but 3 bytes
+        let e = parse_shapes("\u{3000}-\u{3000}Kreis\n").unwrap_err();
+        assert_eq!((e.line, e.column), (1, 4));
+    }
+
+    #[test]
+    fn test_wrong_point_count() {
+        let e = parse_shapes("- Circle\n  P, 1.0, 2.0\n#\n").unwrap_err();
+        assert_eq!((e.line, e.column), (2, 3));
+        assert_eq!(e.kind, SyntaxErrorKind::WrongPointCount {
+            shape: ShapeKind::Circle, expected: PointArity::Exact(3), found: 2,
+        });
+    }
+
+    #[test]
+    fn test_malformed_color() {
+        let e = parse_shapes("- Circle\n  P, 1.0, 2.0, 3.0\n  C, 1.0, x, 0.0, 1.0\n#\n").unwrap_err();
+        assert_eq!((e.line, e.column), (3, 11));
+        let e = parse_shapes("- Circle\n  P, 1.0, 2.0, 3.0\n  C, 1.0, 0.0, 1.0\n#\n").unwrap_err();
+        assert_eq!((e.line, e.column), (3, 3));
+        assert!(matches!(e.kind, SyntaxErrorKind::MalformedColor(_)));
+    }
+
+    #[test]
+    fn test_negative_size() {
+        let e = parse_shapes("- Circle\n  P, 1.0, 2.0, -3.0\n#\n").unwrap_err();
+        assert_eq!((e.line, e.column), (2, 3));
+        assert_eq!(e.kind, SyntaxErrorKind::InvalidBlock(BlockError::NegativeSize { shape: ShapeKind::Circle, value: -3.0 }));
+        let e = parse_shapes("- Circle\n  P, 1.0, 2.0, inf\n#\n").unwrap_err();
+        assert_eq!(e.kind, SyntaxErrorKind::InvalidBlock(BlockError::NotFinite { shape: ShapeKind::Circle, value: f32::INFINITY }));
+    }
+
+    #[test]
+    fn test_unterminated_shape() {
+        let e = parse_shapes("- Circle\n  P, 1.0, 2.0, 3.0\n").unwrap_err();
+        assert_eq!(e.kind, SyntaxErrorKind::UnterminatedShape);
+        assert_eq!(e.line, 1);
+    }
+}
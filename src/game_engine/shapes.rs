@@ -1,17 +1,171 @@
-use std::collections::HashMap;
-use crate::BLACK;
-use std::convert::TryInto;
+use std::fmt;
+use mint::{Point2, Vector2};
+use crate::game_engine::transform::Transform;
 
 #[derive(Copy, Clone, Debug, PartialOrd, PartialEq)]
 pub enum ShapeKind {
     Rect,
+    /// closed, last point is connected back to the first
     Polygon,
+    /// open, like a polygon without the closing edge
+    Polyline,
     Line,
     Ellipse,
     Circle,
     None
 }
 
+/// How many values a shape expects in its points list
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum PointArity {
+    /// exactly this many values
+    Exact(usize),
+    /// x,y pairs, at least this many values
+    Pairs(usize),
+    /// shape does not take any points
+    Nothing,
+}
+
+impl PointArity {
+    pub fn accepts(&self, count: usize) -> bool {
+        match *self {
+            PointArity::Exact(n) => count == n,
+            PointArity::Pairs(min) => count >= min && count.is_multiple_of(2),
+            PointArity::Nothing => count == 0,
+        }
+    }
+}
+
+impl fmt::Display for PointArity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            PointArity::Exact(n) => write!(f, "exactly {} values", n),
+            PointArity::Pairs(min) => write!(f, "x,y pairs with at least {} values", min),
+            PointArity::Nothing => write!(f, "no values"),
+        }
+    }
+}
+
+impl ShapeKind {
+    /// parses shape names used in shape files, e.g. `Polygon` or `Circle`
+    pub fn from_name(name: &str) -> Option<ShapeKind> {
+        match name {
+            "Rect" => Some(ShapeKind::Rect),
+            "Polygon" => Some(ShapeKind::Polygon),
+            "Polyline" => Some(ShapeKind::Polyline),
+            "Line" => Some(ShapeKind::Line),
+            "Ellipse" => Some(ShapeKind::Ellipse),
+            "Circle" => Some(ShapeKind::Circle),
+            _ => None,
+        }
+    }
+
+    /// number of values the points list needs for this shape
+    /// Rect: x, y, w, h
+    /// Circle: x, y, r
+    /// Ellipse: x, y, a, b
+    /// Line: x1, y1, x2, y2
+    /// Polygon, Polyline: x, y pairs
+    pub fn point_arity(&self) -> PointArity {
+        match self {
+            ShapeKind::Rect => PointArity::Exact(4),
+            ShapeKind::Circle => PointArity::Exact(3),
+            ShapeKind::Ellipse => PointArity::Exact(4),
+            ShapeKind::Line => PointArity::Exact(4),
+            ShapeKind::Polygon | ShapeKind::Polyline => PointArity::Pairs(4),
+            ShapeKind::None => PointArity::Nothing,
+        }
+    }
+}
+
+pub type Point = Point2<f32>;
+pub type Size = Vector2<f32>;
+
+/// Geometry of a block, relative to the block position
+#[derive(Clone, Debug, PartialEq)]
+pub enum Shape {
+    Rect { offset: Point, size: Size },
+    Circle { center: Point, radius: f32 },
+    Ellipse { center: Point, radii: Size },
+    Line { from: Point, to: Point },
+    /// closed, last point is connected back to the first
+    Polygon(Vec<Point>),
+    /// open, like a polygon without the closing edge
+    Polyline(Vec<Point>),
+}
+
+impl Shape {
+    pub fn kind(&self) -> ShapeKind {
+        match self {
+            Shape::Rect { .. } => ShapeKind::Rect,
+            Shape::Circle { .. } => ShapeKind::Circle,
+            Shape::Ellipse { .. } => ShapeKind::Ellipse,
+            Shape::Line { .. } => ShapeKind::Line,
+            Shape::Polygon(_) => ShapeKind::Polygon,
+            Shape::Polyline(_) => ShapeKind::Polyline,
+        }
+    }
+
+    /// reads a raw points list, laid out as described in `ShapeKind::point_arity`
+    pub fn from_points(kind: ShapeKind, points: &[f32]) -> Result<Shape, BlockError> {
+        if kind == ShapeKind::None {
+            return Err(BlockError::MissingShape);
+        }
+        validate_points(kind, points)?;
+        let p = |i: usize| Point { x: points[i], y: points[i + 1] };
+        let pairs = || points.chunks_exact(2).map(|c| Point { x: c[0], y: c[1] }).collect();
+        Ok(match kind {
+            ShapeKind::Rect => Shape::Rect { offset: p(0), size: Size { x: points[2], y: points[3] } },
+            ShapeKind::Circle => Shape::Circle { center: p(0), radius: points[2] },
+            ShapeKind::Ellipse => Shape::Ellipse { center: p(0), radii: Size { x: points[2], y: points[3] } },
+            ShapeKind::Line => Shape::Line { from: p(0), to: p(2) },
+            ShapeKind::Polygon => Shape::Polygon(pairs()),
+            ShapeKind::Polyline => Shape::Polyline(pairs()),
+            ShapeKind::None => unreachable!(),
+        })
+    }
+
+    /// the raw points list, the inverse of `from_points`
+    pub fn to_points(&self) -> Vec<f32> {
+        match self {
+            Shape::Rect { offset, size } => vec![offset.x, offset.y, size.x, size.y],
+            Shape::Circle { center, radius } => vec![center.x, center.y, *radius],
+            Shape::Ellipse { center, radii } => vec![center.x, center.y, radii.x, radii.y],
+            Shape::Line { from, to } => vec![from.x, from.y, to.x, to.y],
+            Shape::Polygon(points) | Shape::Polyline(points) => points.iter().flat_map(|p| vec![p.x, p.y]).collect(),
+        }
+    }
+
+    /// same checks as `validate_points`, polygons need 2 points, values must be finite and sizes can not be negative
+    pub fn validate(&self) -> Result<(), BlockError> {
+        validate_points(self.kind(), &self.to_points())
+    }
+}
+
+/// How rectangles, circles, ellipses and polygons are drawn, lines only have an outline
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum FillMode {
+    /// only the outline, in the block color
+    Outline,
+    /// the whole shape, in the block color
+    Filled,
+    /// filled with the block color, outline with the outline color
+    Both,
+}
+
+/// How a block is combined with the pixels already drawn beneath it
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum BlendMode {
+    /// overwrite whatever was there
+    Replace,
+    /// regular alpha compositing, block color over the pixels beneath
+    AlphaOver,
+    /// adds the color weighted by its alpha, good for highlights
+    Additive,
+    /// multiplies with the color beneath, good for shadows and fog
+    Multiply,
+}
+
 #[derive(Copy, Clone, Debug)]
 pub struct Block {
     pub x: f32,
@@ -19,12 +173,116 @@ pub struct Block {
     pub color: [f32; 4],
     pub shape: ShapeKind,
     pub index: usize,
+    pub fill: FillMode,
+    pub outline_color: [f32; 4],
+    /// outline width in pixels, rect, circle and ellipse outlines grow inwards,
+    /// polygon outlines and lines are centered on their path
+    pub stroke: f32,
+    pub blend: BlendMode,
+    /// blocks on higher layers are drawn over lower ones
+    pub layer: i32,
+    /// rotation and scale applied to the geometry before it is moved to `x`, `y`
+    pub transform: Transform,
+}
+
+/// widest outline a block can have, wide lines paint stroke² pixels for every pixel along the line
+pub const MAX_STROKE: f32 = 256.0;
+
+/// largest position or pivot a block can have, f32 can not step through pixels much further out
+pub const MAX_COORDINATE: f32 = 1_048_576.0;
+
+/// largest scale factor of a block's transform
+pub const MAX_SCALE: f32 = 1024.0;
+
+/// Why a `BlockBuilder` can not build its block
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum BlockError {
+    /// built from `BlockBuilder::empty`
+    MissingShape,
+    /// `points` was never called
+    MissingPoints,
+    WrongPointCount { shape: ShapeKind, expected: PointArity, found: usize },
+    /// color needs 4 values, r, g, b and a
+    MalformedColor { found: usize },
+    /// color value outside 0.0..=1.0
+    ColorOutOfRange(f32),
+    /// width, height or radius below zero
+    NegativeSize { shape: ShapeKind, value: f32 },
+    /// a point value is infinite or NaN
+    NotFinite { shape: ShapeKind, value: f32 },
+    /// geometry of another kind than the block
+    ShapeMismatch { expected: ShapeKind, found: ShapeKind },
+    /// stroke width negative, not finite or above `MAX_STROKE`
+    InvalidStroke(f32),
+    /// position, rotation, scale or pivot not finite, or too large to draw, see `MAX_COORDINATE` and `MAX_SCALE`
+    InvalidTransform(f32),
+}
+
+impl fmt::Display for BlockError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BlockError::MissingShape => write!(f, "block has no shape"),
+            BlockError::MissingPoints => write!(f, "block has no points"),
+            BlockError::WrongPointCount { shape, expected, found } =>
+                write!(f, "{:?} expects {}, found {}", shape, expected, found),
+            BlockError::MalformedColor { found } =>
+                write!(f, "expected 4 color values (r, g, b, a), found {}", found),
+            BlockError::ColorOutOfRange(value) => write!(f, "color value {} is outside 0.0..=1.0", value),
+            BlockError::NegativeSize { shape, value } => write!(f, "{:?} has negative size {}", shape, value),
+            BlockError::NotFinite { shape, value } => write!(f, "{:?} has non-finite value {}", shape, value),
+            BlockError::ShapeMismatch { expected, found } =>
+                write!(f, "expected {:?} geometry, found {:?}", expected, found),
+            BlockError::InvalidStroke(width) => write!(f, "stroke width {} is outside 0.0..={}", width, MAX_STROKE),
+            BlockError::InvalidTransform(value) => write!(f, "transform value {} is not finite or too large", value),
+        }
+    }
+}
+
+impl std::error::Error for BlockError {}
+
+/// checks the points list of a shape, the number of values, that all are finite and sizes are not negative
+pub fn validate_points(shape: ShapeKind, points: &[f32]) -> Result<(), BlockError> {
+    let expected = shape.point_arity();
+    if !expected.accepts(points.len()) {
+        return Err(BlockError::WrongPointCount { shape, expected, found: points.len() });
+    }
+    // the rasterizers never finish an infinite size
+    if let Some(&value) = points.iter().find(|v| !v.is_finite()) {
+        return Err(BlockError::NotFinite { shape, value });
+    }
+    let sizes = match shape {
+        ShapeKind::Rect | ShapeKind::Ellipse => &points[2..4],
+        ShapeKind::Circle => &points[2..3],
+        _ => &[],
+    };
+    match sizes.iter().find(|v| **v < 0.0) {
+        Some(&value) => Err(BlockError::NegativeSize { shape, value }),
+        None => Ok(()),
+    }
+}
+
+/// the drawing loops step one pixel at a time, so mapped geometry has to stay in reach
+fn validate_transform(block: &Block) -> Result<(), BlockError> {
+    let t = &block.transform;
+    let coordinates = [block.x, block.y, t.pivot.x, t.pivot.y];
+    if let Some(&value) = coordinates.iter().find(|v| !(-MAX_COORDINATE..=MAX_COORDINATE).contains(*v)) {
+        return Err(BlockError::InvalidTransform(value));
+    }
+    if let Some(&value) = [t.scale.x, t.scale.y].iter().find(|v| !(-MAX_SCALE..=MAX_SCALE).contains(*v)) {
+        return Err(BlockError::InvalidTransform(value));
+    }
+    if !t.rotation.is_finite() {
+        return Err(BlockError::InvalidTransform(t.rotation));
+    }
+    Ok(())
 }
 
 #[derive(Copy, Clone, Debug)]
 pub struct BlockBuilder {
     block: Block,
     index: Option<usize>,
+    /// first problem found by a setter, reported by `try_build`
+    error: Option<BlockError>,
 }
 
 impl BlockBuilder {
@@ -34,7 +292,13 @@ impl BlockBuilder {
                 x: 0.0, y: 0.0,
                 color: [0.0, 0.0, 0.0, 1.0],
                 shape: ShapeKind::None, index: 0,
-            }, index: None
+                fill: FillMode::Outline,
+                outline_color: [0.0, 0.0, 0.0, 1.0],
+                stroke: 1.0,
+                blend: BlendMode::Replace,
+                layer: 0,
+                transform: Transform::default(),
+            }, index: None, error: None
         }
     }
 
@@ -44,7 +308,13 @@ impl BlockBuilder {
                 x, y,
                 color: [1.0, 1.0, 1.0, 1.0],
                 shape: s, index: 0,
-            }, index: None
+                fill: if s == ShapeKind::Rect { FillMode::Filled } else { FillMode::Outline },
+                outline_color: [1.0, 1.0, 1.0, 1.0],
+                stroke: 1.0,
+                blend: BlendMode::Replace,
+                layer: 0,
+                transform: Transform::default(),
+            }, index: None, error: None
         }
     }
 
@@ -68,33 +338,157 @@ impl BlockBuilder {
         BlockBuilder::new(ShapeKind::Polygon, x, y)
     }
 
+    pub fn polyline(x: f32, y: f32) -> Self {
+        BlockBuilder::new(ShapeKind::Polyline, x, y)
+    }
+
     pub fn color(&mut self, color: Vec<f32>) -> &mut Self {
-        self.block.color = [color[0], color[1], color[2], color[3]];
+        if let Some(color) = self.check_color(&color) {
+            self.block.color = color;
+        }
         self
     }
 
-    pub fn points(&mut self, points: Vec<f32>, points_list: &mut Vec<Vec<f32>>) -> &mut Self {
-        self.index = Some(points_list.len());
-        points_list.push(points);
+    pub fn fill(&mut self, fill: FillMode) -> &mut Self {
+        self.block.fill = fill;
         self
     }
 
+    /// outline color, used with `FillMode::Both`
+    pub fn outline_color(&mut self, color: Vec<f32>) -> &mut Self {
+        if let Some(color) = self.check_color(&color) {
+            self.block.outline_color = color;
+        }
+        self
+    }
+
+    fn check_color(&mut self, color: &[f32]) -> Option<[f32; 4]> {
+        let error = match *color {
+            [r, g, b, a] => match color.iter().find(|v| !(0.0..=1.0).contains(*v)) {
+                Some(&v) => BlockError::ColorOutOfRange(v),
+                None => return Some([r, g, b, a]),
+            },
+            _ => BlockError::MalformedColor { found: color.len() },
+        };
+        self.error.get_or_insert(error);
+        None
+    }
+
+    pub fn stroke(&mut self, width: f32) -> &mut Self {
+        self.block.stroke = width;
+        self
+    }
+
+    pub fn blend(&mut self, blend: BlendMode) -> &mut Self {
+        self.block.blend = blend;
+        self
+    }
+
+    pub fn layer(&mut self, layer: i32) -> &mut Self {
+        self.block.layer = layer;
+        self
+    }
+
+    /// clockwise rotation in radians, around the pivot
+    pub fn rotation(&mut self, radians: f32) -> &mut Self {
+        self.block.transform.rotation = radians;
+        self
+    }
+
+    pub fn rotation_degrees(&mut self, degrees: f32) -> &mut Self {
+        self.rotation(degrees.to_radians())
+    }
+
+    /// scale along x and y, around the pivot
+    pub fn scale(&mut self, x: f32, y: f32) -> &mut Self {
+        self.block.transform.scale = Size { x, y };
+        self
+    }
+
+    /// point rotated and scaled around, in the geometry's coordinates
+    pub fn pivot(&mut self, x: f32, y: f32) -> &mut Self {
+        self.block.transform.pivot = Point { x, y };
+        self
+    }
+
+    pub fn transform(&mut self, transform: Transform) -> &mut Self {
+        self.block.transform = transform;
+        self
+    }
+
+    /// adds the raw points as new geometry, see `ShapeKind::point_arity` for the layout
+    pub fn points(&mut self, points: Vec<f32>, shapes: &mut Vec<Shape>) -> &mut Self {
+        match Shape::from_points(self.block.shape, &points) {
+            Ok(shape) => self.geometry(shape, shapes),
+            // `try_build` reports blocks without shape
+            Err(BlockError::MissingShape) => self,
+            Err(e) => {
+                self.error.get_or_insert(e);
+                self
+            }
+        }
+    }
+
+    /// adds the geometry to the list, it has to be of the block's kind
+    pub fn geometry(&mut self, shape: Shape, shapes: &mut Vec<Shape>) -> &mut Self {
+        if shape.kind() != self.block.shape {
+            self.error.get_or_insert(BlockError::ShapeMismatch { expected: self.block.shape, found: shape.kind() });
+            return self;
+        }
+        if let Err(e) = shape.validate() {
+            self.error.get_or_insert(e);
+            return self;
+        }
+        self.index = Some(shapes.len());
+        shapes.push(shape);
+        self
+    }
+
+    /// uses geometry already in the list, so many blocks can share it
+    pub fn shared_geometry(&mut self, index: usize, shapes: &[Shape]) -> &mut Self {
+        match shapes.get(index) {
+            Some(shape) if shape.kind() != self.block.shape => {
+                self.error.get_or_insert(BlockError::ShapeMismatch { expected: self.block.shape, found: shape.kind() });
+            }
+            Some(_) => self.index = Some(index),
+            None => {
+                self.error.get_or_insert(BlockError::MissingPoints);
+            }
+        }
+        self
+    }
+
+    /// panics if the block is invalid, see `try_build`
     pub fn build(&mut self) -> Block {
+        self.try_build().unwrap_or_else(|e| panic!("invalid block: {}", e))
+    }
+
+    pub fn try_build(&mut self) -> Result<Block, BlockError> {
         if self.block.shape == ShapeKind::None {
-            panic!("Block is not initialized");
+            return Err(BlockError::MissingShape);
+        }
+        if let Some(e) = self.error {
+            return Err(e);
         }
-        if self.index.is_none() {
-            panic!("Position information not initialized");
+        self.block.index = self.index.ok_or(BlockError::MissingPoints)?;
+        if !(0.0..=MAX_STROKE).contains(&self.block.stroke) {
+            return Err(BlockError::InvalidStroke(self.block.stroke));
         }
-        self.block.index = self.index.unwrap();
-        return self.block;
+        validate_transform(&self.block)?;
+        Ok(self.block)
     }
 }
 
 impl Block {
 
-    pub fn get_shape_info(&self, point_list: &Vec<Vec<f32>>) -> Vec<f32> {
-        point_list[self.index].to_vec()
+    /// raw points of the block's geometry, see `Shape::to_points`
+    pub fn get_shape_info(&self, shapes: &[Shape]) -> Vec<f32> {
+        shapes[self.index].to_points()
+    }
+
+    /// the block's geometry, `None` if the index is outside of the list
+    pub fn get_shape<'a>(&self, shapes: &'a [Shape]) -> Option<&'a Shape> {
+        shapes.get(self.index)
     }
 
     pub fn get_shape_kind(&self) -> ShapeKind {
@@ -104,5 +498,98 @@ impl Block {
     pub fn get_index(&self) -> usize {
         self.index
     }
+
+    /// color the inside is filled with, if it is filled
+    pub fn get_fill_color(&self) -> Option<[f32; 4]> {
+        match self.fill {
+            FillMode::Outline => None,
+            FillMode::Filled | FillMode::Both => Some(self.color),
+        }
+    }
+
+    /// color of the outline, if it has one
+    pub fn get_outline_color(&self) -> Option<[f32; 4]> {
+        match self.fill {
+            FillMode::Outline => Some(self.color),
+            FillMode::Filled => None,
+            FillMode::Both => Some(self.outline_color),
+        }
+    }
 }
 
+#[cfg(test)]
+mod tests {
+    use crate::game_engine::shapes::{BlockBuilder, BlockError, Point, PointArity, Shape, ShapeKind, Size};
+
+    #[test]
+    fn test_try_build() {
+        let mut points = Vec::new();
+        let block = BlockBuilder::circle(1.0, 1.0).points(vec![0.0, 0.0, 2.0], &mut points).try_build().unwrap();
+        assert_eq!(block.get_index(), 0);
+        assert_eq!(BlockBuilder::empty().points(vec![], &mut points).try_build().unwrap_err(), BlockError::MissingShape);
+        assert_eq!(BlockBuilder::rect(0.0, 0.0).try_build().unwrap_err(), BlockError::MissingPoints);
+        assert_eq!(
+            BlockBuilder::circle(0.0, 0.0).points(vec![0.0, 0.0, 2.0, 3.0], &mut points).try_build().unwrap_err(),
+            BlockError::WrongPointCount { shape: ShapeKind::Circle, expected: PointArity::Exact(3), found: 4 }
+        );
+        assert_eq!(
+            BlockBuilder::rect(0.0, 0.0).points(vec![0.0, 0.0, -2.0, 3.0], &mut points).try_build().unwrap_err(),
+            BlockError::NegativeSize { shape: ShapeKind::Rect, value: -2.0 }
+        );
+        assert_eq!(
+            BlockBuilder::rect(0.0, 0.0).points(vec![0.0, 0.0, f32::INFINITY, 3.0], &mut points).try_build().unwrap_err(),
+            BlockError::NotFinite { shape: ShapeKind::Rect, value: f32::INFINITY }
+        );
+        assert!(matches!(
+            BlockBuilder::polygon(0.0, 0.0).points(vec![0.0, f32::NAN, 1.0, 1.0], &mut points).try_build(),
+            Err(BlockError::NotFinite { shape: ShapeKind::Polygon, .. })
+        ));
+        let mut builder = BlockBuilder::line(0.0, 0.0);
+        builder.points(vec![0.0, 0.0, -2.0, 3.0], &mut points);
+        assert!(builder.try_build().is_ok());
+        assert_eq!(builder.color(vec![1.0, 0.0]).try_build().unwrap_err(), BlockError::MalformedColor { found: 2 });
+        assert_eq!(
+            BlockBuilder::line(0.0, 0.0).points(vec![0.0; 4], &mut points).outline_color(vec![1.0, 2.0, 0.0, 1.0])
+                .try_build().unwrap_err(),
+            BlockError::ColorOutOfRange(2.0)
+        );
+        let mut line = BlockBuilder::line(0.0, 0.0);
+        line.points(vec![0.0; 4], &mut points);
+        assert_eq!(line.stroke(-1.0).try_build().unwrap_err(), BlockError::InvalidStroke(-1.0));
+        assert_eq!(line.stroke(1e9).try_build().unwrap_err(), BlockError::InvalidStroke(1e9));
+        assert!(matches!(line.stroke(f32::NAN).try_build(), Err(BlockError::InvalidStroke(_))));
+        assert!(line.stroke(0.0).try_build().is_ok());
+        assert_eq!(line.scale(1e30, 1.0).try_build().unwrap_err(), BlockError::InvalidTransform(1e30));
+        assert!(matches!(line.scale(1.0, 1.0).rotation(f32::NAN).try_build(), Err(BlockError::InvalidTransform(_))));
+        assert!(matches!(line.rotation(0.0).pivot(f32::INFINITY, 0.0).try_build(), Err(BlockError::InvalidTransform(_))));
+        let far = BlockBuilder::line(1e9, 0.0).points(vec![0.0; 4], &mut points).try_build();
+        assert_eq!(far.unwrap_err(), BlockError::InvalidTransform(1e9));
+    }
+
+    #[test]
+    fn test_typed_geometry() {
+        let shape = Shape::from_points(ShapeKind::Polygon, &[0.0, 1.0, 2.0, 3.0]).unwrap();
+        assert_eq!(shape, Shape::Polygon(vec![Point { x: 0.0, y: 1.0 }, Point { x: 2.0, y: 3.0 }]));
+        assert_eq!(shape.to_points(), vec![0.0, 1.0, 2.0, 3.0]);
+        assert_eq!(Shape::from_points(ShapeKind::Ellipse, &[1.0, 2.0, 3.0, 4.0]).unwrap(),
+            Shape::Ellipse { center: Point { x: 1.0, y: 2.0 }, radii: Size { x: 3.0, y: 4.0 } });
+
+        let mut shapes = Vec::new();
+        let square = BlockBuilder::rect(0.0, 0.0)
+            .geometry(Shape::Rect { offset: Point { x: 0.0, y: 0.0 }, size: Size { x: 2.0, y: 2.0 } }, &mut shapes)
+            .build();
+        // a second block sharing the geometry, nothing is added to the list
+        let other = BlockBuilder::rect(5.0, 5.0).shared_geometry(square.get_index(), &shapes).build();
+        assert_eq!((shapes.len(), other.get_index()), (1, 0));
+        assert_eq!(
+            BlockBuilder::circle(0.0, 0.0).shared_geometry(0, &shapes).try_build().unwrap_err(),
+            BlockError::ShapeMismatch { expected: ShapeKind::Circle, found: ShapeKind::Rect }
+        );
+        assert_eq!(
+            BlockBuilder::circle(0.0, 0.0)
+                .geometry(Shape::Circle { center: Point { x: 0.0, y: 0.0 }, radius: -1.0 }, &mut shapes)
+                .try_build().unwrap_err(),
+            BlockError::NegativeSize { shape: ShapeKind::Circle, value: -1.0 }
+        );
+    }
+}
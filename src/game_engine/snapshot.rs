@@ -0,0 +1,207 @@
+//! # Snapshot
+//! Deterministic text snapshots of a `PixelStore`, used to compare rasterizer output
+//! against checked in golden files.
+//!
+//! A snapshot has one `x,y r,g,b,a` line per pixel, sorted by row then column.
+//! Lines starting with `#` are comments, the header contains a preview of the pixels.
+//! Set `UPDATE_SNAPSHOTS=1` to rewrite golden files instead of comparing against them.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use graphics::types::Color;
+use crate::game_engine::game_board::PixelStore;
+
+/// pixels by rounded position, so `-0` and `0` end up the same pixel
+type SnapshotPixels = BTreeMap<(i64, i64), Color>;
+
+const PREVIEW_CHARS: &[u8] = b"#@%*+=o-:~abcdefghijklmnopqrstuvwxyz";
+
+#[derive(Debug, Default, PartialEq)]
+pub struct SnapshotDiff {
+    /// pixels only in the actual output
+    pub added: Vec<((i64, i64), Color)>,
+    /// pixels only in the golden file
+    pub missing: Vec<((i64, i64), Color)>,
+    /// pixels in both, with the expected and actual color
+    pub recolored: Vec<((i64, i64), Color, Color)>,
+}
+
+impl SnapshotDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.missing.is_empty() && self.recolored.is_empty()
+    }
+}
+
+impl fmt::Display for SnapshotDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f, "{} added, {} missing, {} recolored",
+            self.added.len(), self.missing.len(), self.recolored.len()
+        )?;
+        for ((x, y), color) in &self.added {
+            writeln!(f, "+ {},{} {}", x, y, format_color(color))?;
+        }
+        for ((x, y), color) in &self.missing {
+            writeln!(f, "- {},{} {}", x, y, format_color(color))?;
+        }
+        for ((x, y), expected, actual) in &self.recolored {
+            writeln!(f, "~ {},{} {} -> {}", x, y, format_color(expected), format_color(actual))?;
+        }
+        Ok(())
+    }
+}
+
+fn format_color(color: &Color) -> String {
+    format!("{},{},{},{}", color[0], color[1], color[2], color[3])
+}
+
+fn collect<S: PixelStore>(pixels: &S) -> SnapshotPixels {
+    pixels.pixels()
+        .map(|p| ((p.point.x.round() as i64, p.point.y.round() as i64), p.color))
+        .collect()
+}
+
+fn by_row(pixels: &SnapshotPixels) -> Vec<(&(i64, i64), &Color)> {
+    let mut sorted: Vec<_> = pixels.iter().collect();
+    sorted.sort_by_key(|((x, y), _)| (*y, *x));
+    sorted
+}
+
+/// serializes the pixels, output only depends on the pixels and not on map order
+pub fn to_snapshot<S: PixelStore>(pixels: &S) -> String {
+    let pixels = collect(pixels);
+    let mut out = String::from("# pixel snapshot\n");
+    let mut colors: Vec<String> = pixels.values().map(format_color).collect();
+    colors.sort();
+    colors.dedup();
+    for (i, color) in colors.iter().enumerate() {
+        out += &format!("# {} = {}\n", preview_char(i), color);
+    }
+    if let (Some(min_x), Some(max_x)) = (pixels.keys().map(|k| k.0).min(), pixels.keys().map(|k| k.0).max()) {
+        let min_y = pixels.keys().map(|k| k.1).min().unwrap();
+        let max_y = pixels.keys().map(|k| k.1).max().unwrap();
+        out += &format!("# preview from {},{}\n", min_x, min_y);
+        for y in min_y..=max_y {
+            let row: String = (min_x..=max_x).map(|x| match pixels.get(&(x, y)) {
+                Some(color) => {
+                    let i = colors.binary_search(&format_color(color)).unwrap();
+                    preview_char(i)
+                }
+                None => '.',
+            }).collect();
+            out += format!("# {}", row.trim_end_matches('.')).trim_end();
+            out.push('\n');
+        }
+    }
+    for ((x, y), color) in by_row(&pixels) {
+        out += &format!("{},{} {}\n", x, y, format_color(color));
+    }
+    out
+}
+
+fn preview_char(i: usize) -> char {
+    PREVIEW_CHARS[i % PREVIEW_CHARS.len()] as char
+}
+
+fn parse_snapshot(snapshot: &str) -> Result<SnapshotPixels, String> {
+    let mut pixels = SnapshotPixels::new();
+    for (i, line) in snapshot.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let invalid = || format!("line {}: invalid snapshot entry `{}`", i + 1, line);
+        let (position, color) = line.split_once(' ').ok_or_else(invalid)?;
+        let position: Vec<i64> = position.split(',')
+            .map(|v| v.parse().map_err(|_| invalid()))
+            .collect::<Result<_, _>>()?;
+        let color: Vec<f32> = color.split(',')
+            .map(|v| v.parse().map_err(|_| invalid()))
+            .collect::<Result<_, _>>()?;
+        if position.len() != 2 || color.len() != 4 {
+            return Err(invalid());
+        }
+        pixels.insert((position[0], position[1]), [color[0], color[1], color[2], color[3]]);
+    }
+    Ok(pixels)
+}
+
+fn diff_pixels(expected: &SnapshotPixels, actual: &SnapshotPixels) -> SnapshotDiff {
+    let mut diff = SnapshotDiff::default();
+    for (position, color) in by_row(actual) {
+        match expected.get(position) {
+            None => diff.added.push((*position, *color)),
+            Some(old) if old != color => diff.recolored.push((*position, *old, *color)),
+            _ => {}
+        }
+    }
+    for (position, color) in by_row(expected) {
+        if !actual.contains_key(position) {
+            diff.missing.push((*position, *color));
+        }
+    }
+    diff
+}
+
+/// compares two snapshots, comments are ignored
+pub fn diff_snapshots(expected: &str, actual: &str) -> Result<SnapshotDiff, String> {
+    Ok(diff_pixels(&parse_snapshot(expected)?, &parse_snapshot(actual)?))
+}
+
+/// compares the pixels against the golden file, panics with the diff if they differ
+pub fn assert_snapshot<S: PixelStore, P: AsRef<Path>>(golden: P, pixels: &S) {
+    let golden = golden.as_ref();
+    let actual = to_snapshot(pixels);
+    if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        if let Some(dir) = golden.parent() {
+            fs::create_dir_all(dir).unwrap();
+        }
+        fs::write(golden, actual).unwrap();
+        return;
+    }
+    let expected = fs::read_to_string(golden).unwrap_or_else(|e| panic!(
+        "can not read snapshot {}: {}, run with UPDATE_SNAPSHOTS=1 to create it", golden.display(), e
+    ));
+    let diff = diff_snapshots(&expected, &actual)
+        .unwrap_or_else(|e| panic!("invalid snapshot {}: {}", golden.display(), e));
+    if !diff.is_empty() {
+        panic!("snapshot {} does not match\n{}\nactual:\n{}", golden.display(), diff, actual);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{GREEN, RED};
+    use crate::game_engine::game_board::{set_pixel, PixelBuffer};
+    use crate::game_engine::snapshot::{to_snapshot, diff_snapshots};
+
+    #[test]
+    fn test_snapshot_is_sorted() {
+        let mut pixels = PixelBuffer::new();
+        set_pixel(&mut pixels, 2.0, 1.0, RED);
+        set_pixel(&mut pixels, 0.0, 1.0, GREEN);
+        set_pixel(&mut pixels, 1.0, 0.0, RED);
+        let snapshot = to_snapshot(&pixels);
+        let entries: Vec<&str> = snapshot.lines().filter(|l| !l.starts_with('#')).collect();
+        assert_eq!(entries, vec!["1,0 1,0,0,1", "0,1 0,1,0,1", "2,1 1,0,0,1"]);
+        assert!(snapshot.contains("# .@\n# #.@\n"));
+    }
+
+    #[test]
+    fn test_diff_snapshots() {
+        let expected = "# comment\n0,0 1,0,0,1\n1,0 1,0,0,1\n";
+        let actual = "1,0 0,1,0,1\n2,0 1,0,0,1\n";
+        let diff = diff_snapshots(expected, actual).unwrap();
+        assert_eq!(diff.added, vec![((2, 0), RED)]);
+        assert_eq!(diff.missing, vec![((0, 0), RED)]);
+        assert_eq!(diff.recolored, vec![((1, 0), RED, GREEN)]);
+        assert_eq!(
+            diff.to_string(),
+            "1 added, 1 missing, 1 recolored\n+ 2,0 1,0,0,1\n- 0,0 1,0,0,1\n~ 1,0 1,0,0,1 -> 0,1,0,1\n"
+        );
+        assert!(diff_snapshots(expected, expected).unwrap().is_empty());
+        assert!(diff_snapshots("0,0 red", "").is_err());
+    }
+}
@@ -0,0 +1,177 @@
+//! # Software Renderer
+//! Rasterizes a `PixelBuffer` or `PixelGrid` to an RGBA image without any graphics backend,
+//! so frames can be rendered and saved on machines without a GPU.
+
+use std::path::Path;
+use image::{ImageResult, Rgba, RgbaImage};
+use image::imageops::{self, FilterType};
+use graphics::types::Color;
+use crate::BLACK;
+use crate::game_engine::background::Background;
+use crate::game_engine::game_board::PixelStore;
+use crate::game_engine::backend::Frame;
+use crate::game_engine::game_data_model::GameDataModel;
+use crate::game_engine::grid::grid_lines;
+
+/// renders the pixels the same way `game_loop` does, image is the size of the window
+pub fn render_to_image<S: PixelStore, T: GameDataModel>(pixels: &S, model: &T) -> RgbaImage {
+    let width = model.get_window_width().max(0) as u32;
+    let height = model.get_window_height().max(0) as u32;
+    render_frame(&Frame::from_model(pixels, model), width, height)
+}
+
+/// renders a frame handed to a backend into an image of the given size,
+/// a background image is loaded from disk, see `render_frame_onto` to reuse it
+pub fn render_frame(frame: &Frame, width: u32, height: u32) -> RgbaImage {
+    let mut image = RgbaImage::from_pixel(width, height, to_rgba(BLACK));
+    let background = match &frame.background {
+        Background::Image(path) => load_background(path),
+        _ => None,
+    };
+    render_frame_onto(&mut image, frame, background.as_ref());
+    image
+}
+
+/// loads a background image, `None` if it can not be read
+pub fn load_background(path: &Path) -> Option<RgbaImage> {
+    image::open(path).ok().map(|i| i.to_rgba8())
+}
+
+/// renders the frame over `image`, which is only cleared if the frame has a background,
+/// `background_image` is the loaded image of `Background::Image`
+pub fn render_frame_onto(image: &mut RgbaImage, frame: &Frame, background_image: Option<&RgbaImage>) {
+    let (width, height) = image.dimensions();
+    match (&frame.background, background_image) {
+        (Background::Color(color), _) => {
+            let color = to_rgba(*color);
+            image.pixels_mut().for_each(|p| *p = color);
+        }
+        (Background::Image(_), Some(background)) if background.dimensions() == (width, height) => {
+            image.copy_from_slice(background);
+        }
+        (Background::Image(_), Some(background)) => {
+            *image = imageops::resize(background, width, height, FilterType::Nearest);
+        }
+        (Background::Image(_), None) => image.pixels_mut().for_each(|p| *p = to_rgba(BLACK)),
+        (Background::None, _) => {}
+    }
+    let block_width = frame.block_width;
+    let block_height = frame.block_height;
+    let [offset_x, offset_y] = frame.offset;
+    let lines = grid_lines(frame.board_width, frame.board_height, block_width, block_height, &frame.grid);
+    for line in lines {
+        let l = line.points;
+        // lines are 1 pixel wide
+        let (x1, y1) = (l[0] as f32 + offset_x, l[1] as f32 + offset_y);
+        let (x2, y2) = ((l[2] as f32 + offset_x).max(x1 + 1.0), (l[3] as f32 + offset_y).max(y1 + 1.0));
+        fill_rect(image, x1, y1, x2, y2, line.color);
+    }
+    // sorted, so overlapping translucent pixels always blend in the same order
+    let mut sorted: Vec<_> = frame.pixels.pixels().collect();
+    sorted.sort_by(|a, b| a.point.y.total_cmp(&b.point.y).then(a.point.x.total_cmp(&b.point.x)));
+    for pixel in sorted {
+        let x = offset_x + pixel.point.x * block_width;
+        let y = offset_y + pixel.point.y * block_height;
+        fill_rect(image, x, y, x + block_width, y + block_height, pixel.color);
+    }
+}
+
+/// renders the pixels and saves them, format is picked from the extension
+pub fn save_image<S: PixelStore, T: GameDataModel, P: AsRef<Path>>(pixels: &S, model: &T, path: P) -> ImageResult<()> {
+    render_to_image(pixels, model).save(path)
+}
+
+/// fills image pixels whose centers are inside the rectangle (x1, y1) - (x2, y2)
+fn fill_rect(image: &mut RgbaImage, x1: f32, y1: f32, x2: f32, y2: f32, color: Color) {
+    let clamp_x = |v: f32| ((v - 0.5).ceil().max(0.0) as u32).min(image.width());
+    let clamp_y = |v: f32| ((v - 0.5).ceil().max(0.0) as u32).min(image.height());
+    let (from_x, to_x) = (clamp_x(x1), clamp_x(x2));
+    let (from_y, to_y) = (clamp_y(y1), clamp_y(y2));
+    for y in from_y..to_y {
+        for x in from_x..to_x {
+            let p = image.get_pixel_mut(x, y);
+            *p = blend_over(*p, color);
+        }
+    }
+}
+
+/// alpha blends the color over the existing pixel
+fn blend_over(dst: Rgba<u8>, color: Color) -> Rgba<u8> {
+    let a = color[3].clamp(0.0, 1.0);
+    let mix = |d: u8, s: f32| to_u8(s * a + (d as f32 / 255.0) * (1.0 - a));
+    Rgba([
+        mix(dst[0], color[0]),
+        mix(dst[1], color[1]),
+        mix(dst[2], color[2]),
+        to_u8(a + (dst[3] as f32 / 255.0) * (1.0 - a)),
+    ])
+}
+
+fn to_rgba(color: Color) -> Rgba<u8> {
+    Rgba([to_u8(color[0]), to_u8(color[1]), to_u8(color[2]), to_u8(color[3])])
+}
+
+fn to_u8(v: f32) -> u8 {
+    (v.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use image::Rgba;
+    use crate::{GREEN, RED};
+    use crate::game_engine::backend::Frame;
+    use crate::game_engine::background::Background;
+    use crate::game_engine::game_board::{set_pixel, PixelBuffer, PixelMap};
+    use crate::game_engine::game_config::GameConfig;
+    use crate::game_engine::test_support::TestGame;
+    use crate::game_engine::software_renderer::{render_frame_onto, render_to_image};
+
+    #[test]
+    fn test_render_to_image() {
+        let config = GameConfig::parse("width=40\nheight=30\nmap_size=10\nboard_width=20\nboard_height=20").unwrap();
+        let game = TestGame::new(config);
+        let mut pixels = PixelBuffer::new();
+        set_pixel(&mut pixels, 1.0, 1.0, RED);
+        set_pixel(&mut pixels, 9.0, 0.0, GREEN);
+        let image = render_to_image(&pixels, &game);
+        assert_eq!(image.dimensions(), (40, 30));
+        // blocks are 2x2 pixels
+        for &(x, y) in &[(2, 2), (3, 3)] {
+            assert_eq!(*image.get_pixel(x, y), Rgba([255, 0, 0, 255]));
+        }
+        assert_eq!(*image.get_pixel(19, 1), Rgba([0, 255, 0, 255]));
+        assert_eq!(*image.get_pixel(1, 1), Rgba([0, 0, 0, 255]));
+        // grid line every 2 blocks, outside of the board nothing is drawn
+        assert_eq!(*image.get_pixel(4, 1), Rgba([23, 23, 23, 255]));
+        assert_eq!(*image.get_pixel(30, 25), Rgba([0, 0, 0, 255]));
+
+        // a pixel at NaN can not be sorted by partial_cmp, it must not stop the others
+        let mut map = PixelMap::new();
+        set_pixel(&mut map, f32::NAN, 0.0, GREEN);
+        set_pixel(&mut map, 1.0, 1.0, RED);
+        assert_eq!(*render_to_image(&map, &game).get_pixel(2, 2), Rgba([255, 0, 0, 255]));
+    }
+
+    #[test]
+    fn test_background() {
+        let config = GameConfig::parse("width=20\nheight=20\nmap_size=10\nboard_width=20\nboard_height=20\ngrid=false").unwrap();
+        let mut game = TestGame::new(config);
+        game.background = Background::Color([0.0, 0.0, 1.0, 1.0]);
+        let mut first = PixelBuffer::new();
+        set_pixel(&mut first, 0.0, 0.0, RED);
+        let mut image = render_to_image(&first, &game);
+        assert_eq!(*image.get_pixel(10, 10), Rgba([0, 0, 255, 255]));
+
+        // without clearing, the first frame's pixel leaves a trail
+        game.background = Background::None;
+        let mut second = PixelBuffer::new();
+        set_pixel(&mut second, 1.0, 0.0, GREEN);
+        render_frame_onto(&mut image, &Frame::from_model(&second, &game), None);
+        assert_eq!(*image.get_pixel(0, 0), Rgba([255, 0, 0, 255]));
+        assert_eq!(*image.get_pixel(2, 0), Rgba([0, 255, 0, 255]));
+
+        // images that can not be loaded fall back to black
+        game.background = Background::Image("missing.png".into());
+        assert_eq!(*render_to_image(&second, &game).get_pixel(10, 10), Rgba([0, 0, 0, 255]));
+    }
+}
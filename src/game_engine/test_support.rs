@@ -0,0 +1,41 @@
+//! # Test Support
+//! Fixtures shared by the test modules
+
+use crate::game_engine::background::Background;
+use crate::game_engine::game_config::{ConfiguredGame, GameConfig};
+use crate::game_engine::shapes::{Block, Shape};
+
+/// Game with only a config, shared by the tests that need a `GameDataModel`,
+/// `state` holds whatever else a test's game needs
+pub(crate) struct TestGame<S = ()> {
+    pub config: GameConfig,
+    pub points: Vec<Shape>,
+    pub background: Background,
+    pub state: S,
+}
+
+impl TestGame {
+    pub fn new(config: GameConfig) -> Self {
+        TestGame::with_state(config, ())
+    }
+}
+
+impl<S> TestGame<S> {
+    pub fn with_state(config: GameConfig, state: S) -> Self {
+        TestGame { config, points: vec![], background: Background::default(), state }
+    }
+}
+
+impl<S> ConfiguredGame for TestGame<S> {
+    fn get_config(&self) -> &GameConfig {
+        &self.config
+    }
+
+    fn get_drawables(&self) -> (Vec<Block>, &Vec<Shape>) {
+        (vec![], &self.points)
+    }
+
+    fn get_background(&self) -> Background {
+        self.background.clone()
+    }
+}
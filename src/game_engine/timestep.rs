@@ -0,0 +1,147 @@
+//! # Timestep
+//! Runs `update` at a fixed rate no matter how often the backend reports time passing,
+//! so game logic behaves the same on fast and slow machines.
+
+use std::fmt;
+
+/// Why `LoopSettings` would never update the game
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum LoopSettingsError {
+    /// updates per second must be finite and above zero
+    InvalidRate(f64),
+    /// at least one update has to run per backend update
+    NoCatchUpSteps,
+}
+
+impl fmt::Display for LoopSettingsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoopSettingsError::InvalidRate(ups) => write!(f, "invalid updates per second {}", ups),
+            LoopSettingsError::NoCatchUpSteps => write!(f, "max_catch_up_steps must be at least 1"),
+        }
+    }
+}
+
+impl std::error::Error for LoopSettingsError {}
+
+/// How often `game_loop` updates the game
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct LoopSettings {
+    pub updates_per_second: f64,
+    /// most updates run for a single backend update, after a hiccup the rest of the time is dropped
+    pub max_catch_up_steps: u32,
+}
+
+impl Default for LoopSettings {
+    fn default() -> Self {
+        LoopSettings {
+            updates_per_second: 60.0,
+            max_catch_up_steps: 5,
+        }
+    }
+}
+
+impl LoopSettings {
+    pub fn new(updates_per_second: f64) -> Result<Self, LoopSettingsError> {
+        let settings = LoopSettings { updates_per_second, ..LoopSettings::default() };
+        settings.validate()?;
+        Ok(settings)
+    }
+
+    /// the fields are public, so settings built by hand are checked here
+    pub fn validate(&self) -> Result<(), LoopSettingsError> {
+        if !(self.updates_per_second.is_finite() && self.updates_per_second > 0.0) {
+            return Err(LoopSettingsError::InvalidRate(self.updates_per_second));
+        }
+        if self.max_catch_up_steps == 0 {
+            return Err(LoopSettingsError::NoCatchUpSteps);
+        }
+        Ok(())
+    }
+
+    /// length of one update in seconds
+    pub fn step(&self) -> f64 {
+        1.0 / self.updates_per_second
+    }
+}
+
+/// Collects elapsed time and hands it out in fixed steps
+#[derive(Clone, Debug)]
+pub struct FixedTimestep {
+    step: f64,
+    max_steps: u32,
+    accumulator: f64,
+}
+
+impl FixedTimestep {
+    /// panics on settings `LoopSettings::validate` rejects, the game would never update
+    pub fn new(settings: &LoopSettings) -> Self {
+        if let Err(e) = settings.validate() {
+            panic!("{}", e);
+        }
+        FixedTimestep {
+            step: settings.step(),
+            max_steps: settings.max_catch_up_steps,
+            accumulator: 0.0,
+        }
+    }
+
+    pub fn step(&self) -> f64 {
+        self.step
+    }
+
+    /// adds `dt` seconds, returns how many updates to run
+    pub fn advance(&mut self, dt: f64) -> u32 {
+        self.accumulator += dt.max(0.0);
+        let steps = (self.accumulator / self.step).floor();
+        if steps > self.max_steps as f64 {
+            self.accumulator %= self.step;
+            return self.max_steps;
+        }
+        self.accumulator = (self.accumulator - steps * self.step).max(0.0);
+        steps as u32
+    }
+
+    /// how far we are between the last update and the next one, 0.0 to 1.0,
+    /// `ext_dt` is the time passed since the backend last reported time
+    pub fn alpha(&self, ext_dt: f64) -> f64 {
+        ((self.accumulator + ext_dt) / self.step).clamp(0.0, 1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game_engine::timestep::{FixedTimestep, LoopSettings, LoopSettingsError};
+
+    #[test]
+    fn test_fixed_timestep() {
+        let mut timestep = FixedTimestep::new(&LoopSettings::new(4.0).unwrap());
+        assert_eq!(timestep.advance(0.125), 0);
+        assert_eq!(timestep.alpha(0.0), 0.5);
+        assert_eq!(timestep.advance(0.25), 1);
+        assert_eq!(timestep.alpha(0.0), 0.5);
+        assert_eq!(timestep.alpha(0.0625), 0.75);
+        assert_eq!(timestep.alpha(1.0), 1.0);
+        assert_eq!(timestep.advance(0.625), 3);
+        assert_eq!(timestep.alpha(0.0), 0.0);
+        // a long hiccup only catches up `max_catch_up_steps`
+        assert_eq!(timestep.advance(10.0), 5);
+        assert!(timestep.alpha(0.0) < 1.0);
+    }
+
+    #[test]
+    fn test_invalid_loop_settings() {
+        assert_eq!(LoopSettings::new(0.0), Err(LoopSettingsError::InvalidRate(0.0)));
+        assert_eq!(LoopSettings::new(-30.0), Err(LoopSettingsError::InvalidRate(-30.0)));
+        assert!(matches!(LoopSettings::new(f64::NAN), Err(LoopSettingsError::InvalidRate(_))));
+        assert!(matches!(LoopSettings::new(f64::INFINITY), Err(LoopSettingsError::InvalidRate(_))));
+        let settings = LoopSettings { max_catch_up_steps: 0, ..LoopSettings::default() };
+        assert_eq!(settings.validate(), Err(LoopSettingsError::NoCatchUpSteps));
+    }
+
+    #[test]
+    #[should_panic(expected = "max_catch_up_steps")]
+    fn test_timestep_rejects_invalid_settings() {
+        FixedTimestep::new(&LoopSettings { max_catch_up_steps: 0, ..LoopSettings::default() });
+    }
+}
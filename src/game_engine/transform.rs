@@ -0,0 +1,154 @@
+//! # Transform
+//! Rotation, scale and pivot of a block, applied to its geometry before rasterizing.
+
+use std::f32::consts::PI;
+use crate::game_engine::shapes::{Point, Size};
+
+/// Rotation and scale around a pivot, relative to the block position
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Transform {
+    /// clockwise in radians, y points down on the board
+    pub rotation: f32,
+    pub scale: Size,
+    /// point rotated and scaled around, in the same coordinates as the block's geometry
+    pub pivot: Point,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Transform {
+            rotation: 0.0,
+            scale: Size { x: 1.0, y: 1.0 },
+            pivot: Point { x: 0.0, y: 0.0 },
+        }
+    }
+}
+
+impl Transform {
+    pub fn rotation_degrees(&self) -> f32 {
+        self.rotation * 180.0 / PI
+    }
+
+    pub fn is_identity(&self) -> bool {
+        *self == Transform::default()
+    }
+
+    /// scales, then rotates, around the pivot
+    pub fn affine(&self) -> Affine {
+        let (px, py) = (self.pivot.x, self.pivot.y);
+        Affine::translation(px, py)
+            .compose(&Affine::rotation(self.rotation))
+            .compose(&Affine::scale(self.scale.x, self.scale.y))
+            .compose(&Affine::translation(-px, -py))
+    }
+}
+
+/// 2D affine matrix, maps `(x, y)` to `(a x + c y + e, b x + d y + f)`
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Affine {
+    pub a: f32,
+    pub b: f32,
+    pub c: f32,
+    pub d: f32,
+    pub e: f32,
+    pub f: f32,
+}
+
+impl Default for Affine {
+    fn default() -> Self {
+        Affine::identity()
+    }
+}
+
+impl Affine {
+    pub fn identity() -> Self {
+        Affine { a: 1.0, b: 0.0, c: 0.0, d: 1.0, e: 0.0, f: 0.0 }
+    }
+
+    pub fn translation(x: f32, y: f32) -> Self {
+        Affine { e: x, f: y, ..Affine::identity() }
+    }
+
+    pub fn rotation(radians: f32) -> Self {
+        let (sin, cos) = radians.sin_cos();
+        Affine { a: cos, b: sin, c: -sin, d: cos, e: 0.0, f: 0.0 }
+    }
+
+    pub fn scale(x: f32, y: f32) -> Self {
+        Affine { a: x, d: y, ..Affine::identity() }
+    }
+
+    /// `inner` is applied first, then `self`
+    pub fn compose(&self, inner: &Affine) -> Affine {
+        Affine {
+            a: self.a * inner.a + self.c * inner.b,
+            b: self.b * inner.a + self.d * inner.b,
+            c: self.a * inner.c + self.c * inner.d,
+            d: self.b * inner.c + self.d * inner.d,
+            e: self.a * inner.e + self.c * inner.f + self.e,
+            f: self.b * inner.e + self.d * inner.f + self.f,
+        }
+    }
+
+    pub fn apply(&self, p: Point) -> Point {
+        Point {
+            x: self.a * p.x + self.c * p.y + self.e,
+            y: self.b * p.x + self.d * p.y + self.f,
+        }
+    }
+
+    /// the offset if the matrix only moves points
+    pub fn as_translation(&self) -> Option<(f32, f32)> {
+        if self.a == 1.0 && self.b == 0.0 && self.c == 0.0 && self.d == 1.0 {
+            Some((self.e, self.f))
+        } else {
+            None
+        }
+    }
+
+    /// no rotation or shear, axis aligned shapes stay axis aligned
+    pub fn is_axis_aligned(&self) -> bool {
+        self.b == 0.0 && self.c == 0.0
+    }
+
+    /// the uniform scale factor if the matrix only rotates, scales evenly and moves
+    pub fn uniform_scale(&self) -> Option<f32> {
+        let sx = (self.a * self.a + self.b * self.b).sqrt();
+        let sy = (self.c * self.c + self.d * self.d).sqrt();
+        let orthogonal = (self.a * self.c + self.b * self.d).abs() <= 1e-6 * sx * sy;
+        if orthogonal && (sx - sy).abs() <= 1e-6 * sx {
+            Some(sx)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_PI_2;
+    use crate::game_engine::shapes::{Point, Size};
+    use crate::game_engine::transform::{Affine, Transform};
+
+    fn close(p: Point, x: f32, y: f32) -> bool {
+        (p.x - x).abs() < 1e-4 && (p.y - y).abs() < 1e-4
+    }
+
+    #[test]
+    fn test_transform_affine() {
+        let spin = Transform { rotation: FRAC_PI_2, pivot: Point { x: 1.0, y: 1.0 }, ..Transform::default() };
+        assert!((spin.rotation_degrees() - 90.0).abs() < 1e-4);
+        // (2, 1) is right of the pivot, a quarter turn moves it below
+        assert!(close(spin.affine().apply(Point { x: 2.0, y: 1.0 }), 1.0, 2.0));
+        assert!(close(spin.affine().apply(Point { x: 1.0, y: 1.0 }), 1.0, 1.0));
+        assert_eq!(spin.affine().uniform_scale().map(|s| (s - 1.0).abs() < 1e-6), Some(true));
+
+        let grow = Transform { scale: Size { x: 2.0, y: 3.0 }, ..Transform::default() };
+        assert_eq!(grow.affine().apply(Point { x: 1.0, y: 1.0 }), Point { x: 2.0, y: 3.0 });
+        assert!(grow.affine().is_axis_aligned());
+        assert_eq!(grow.affine().uniform_scale(), None);
+
+        let moved = Affine::translation(5.0, 0.0).compose(&Transform::default().affine());
+        assert_eq!(moved.as_translation(), Some((5.0, 0.0)));
+    }
+}